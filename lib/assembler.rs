@@ -0,0 +1,524 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::chip::MAX_PROGRAM_SIZE;
+
+const PROGRAM_START: u16 = 0x200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+fn parse_number(token: &str) -> Option<i64> {
+    let (negative, token) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    let (digits, radix) = if let Some(hex) = token.strip_prefix("0x") {
+        (hex, 16)
+    } else if let Some(bin) = token.strip_prefix("0b") {
+        (bin, 2)
+    } else {
+        (token, 10)
+    };
+    let value = i64::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -value } else { value })
+}
+
+fn parse_register(token: &str) -> Option<u8> {
+    let digit = token.strip_prefix(['v', 'V'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(digit, 16).ok()
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Register(u8),
+    Value(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Condition {
+    Equal(u8, Operand),
+    NotEqual(u8, Operand),
+    Key(u8),
+    NotKey(u8),
+}
+
+impl Condition {
+    fn negate(self) -> Condition {
+        match self {
+            Condition::Equal(x, operand) => Condition::NotEqual(x, operand),
+            Condition::NotEqual(x, operand) => Condition::Equal(x, operand),
+            Condition::Key(x) => Condition::NotKey(x),
+            Condition::NotKey(x) => Condition::Key(x),
+        }
+    }
+
+    // The skip that makes the next instruction run only if the condition holds
+    fn skip_unless(self) -> u16 {
+        let x = |x: u8| (x as u16) << 8;
+        match self {
+            Condition::Equal(vx, Operand::Value(n)) => 0x4000 | x(vx) | n as u16,
+            Condition::NotEqual(vx, Operand::Value(n)) => 0x3000 | x(vx) | n as u16,
+            Condition::Equal(vx, Operand::Register(vy)) => 0x9000 | x(vx) | (vy as u16) << 4,
+            Condition::NotEqual(vx, Operand::Register(vy)) => 0x5000 | x(vx) | (vy as u16) << 4,
+            Condition::Key(vx) => 0xE0A1 | x(vx),
+            Condition::NotKey(vx) => 0xE09E | x(vx),
+        }
+    }
+}
+
+// Open `if ... begin` and `loop` blocks, holding the offsets of the jumps
+// that get patched once the block ends
+enum Block {
+    If {
+        jump: usize,
+        line: usize,
+    },
+    Else {
+        jump: usize,
+        line: usize,
+    },
+    Loop {
+        start: u16,
+        exits: Vec<usize>,
+        line: usize,
+    },
+}
+
+// A jump, call or `i :=` to a label that is not defined yet
+struct Fixup {
+    offset: usize,
+    name: String,
+    line: usize,
+}
+
+struct Assembler<'a> {
+    tokens: Vec<(&'a str, usize)>,
+    next: usize,
+    program: Vec<u8>,
+    labels: HashMap<&'a str, u16>,
+    constants: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, u8>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    line: usize,
+}
+
+impl<'a> Assembler<'a> {
+    fn error<T>(&self, message: String) -> Result<T, AsmError> {
+        Err(AsmError {
+            line: self.line,
+            message,
+        })
+    }
+
+    fn token(&mut self) -> Result<&'a str, AsmError> {
+        match self.tokens.get(self.next) {
+            Some(&(token, line)) => {
+                self.next += 1;
+                self.line = line;
+                Ok(token)
+            }
+            None => self.error("unexpected end of source".to_string()),
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AsmError> {
+        let token = self.token()?;
+        if token != expected {
+            return self.error(format!("expected '{}', found '{}'", expected, token));
+        }
+        Ok(())
+    }
+
+    fn address(&self) -> u16 {
+        PROGRAM_START + self.program.len() as u16
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<(), AsmError> {
+        if self.program.len() >= MAX_PROGRAM_SIZE {
+            return self.error(format!(
+                "program does not fit in memory, at most {} bytes",
+                MAX_PROGRAM_SIZE
+            ));
+        }
+        self.program.push(byte);
+        Ok(())
+    }
+
+    fn emit(&mut self, opcode: u16) -> Result<(), AsmError> {
+        let [high, low] = opcode.to_be_bytes();
+        self.emit_byte(high)?;
+        self.emit_byte(low)
+    }
+
+    fn patch(&mut self, offset: usize, address: u16) {
+        self.program[offset] = (self.program[offset] & 0xF0) | (address >> 8) as u8;
+        self.program[offset + 1] = address as u8;
+    }
+
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let token = self.token()?;
+        match self.aliases.get(token).copied().or(parse_register(token)) {
+            Some(register) => Ok(register),
+            None => self.error(format!("expected a register, found '{}'", token)),
+        }
+    }
+
+    fn number(&self, token: &str) -> Option<i64> {
+        parse_number(token).or(self.constants.get(token).copied())
+    }
+
+    fn value(&mut self, min: i64, max: i64) -> Result<i64, AsmError> {
+        let token = self.token()?;
+        match self.number(token) {
+            Some(value) if (min..=max).contains(&value) => Ok(value),
+            Some(value) => self.error(format!("{} is out of range {}..{}", value, min, max)),
+            None => self.error(format!("expected a number, found '{}'", token)),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, AsmError> {
+        Ok(self.value(-128, 255)? as u8)
+    }
+
+    fn operand(&mut self) -> Result<Operand, AsmError> {
+        let token = self.token()?;
+        if let Some(register) = self.aliases.get(token).copied().or(parse_register(token)) {
+            return Ok(Operand::Register(register));
+        }
+        match self.number(token) {
+            Some(value) if (-128..=255).contains(&value) => Ok(Operand::Value(value as u8)),
+            Some(value) => self.error(format!("{} does not fit in a byte", value)),
+            None => self.error(format!(
+                "expected a register or a number, found '{}'",
+                token
+            )),
+        }
+    }
+
+    // Emits `opcode` with the address of a label, a constant or a number
+    fn emit_with_address(&mut self, opcode: u16) -> Result<(), AsmError> {
+        let token = self.token()?;
+        let address = match self.labels.get(token) {
+            Some(&address) => address as i64,
+            None => match self.number(token) {
+                Some(value) => value,
+                None if token.starts_with(|c: char| c.is_alphabetic() || c == '_') => {
+                    self.fixups.push(Fixup {
+                        offset: self.program.len(),
+                        name: token.to_string(),
+                        line: self.line,
+                    });
+                    0
+                }
+                None => return self.error(format!("expected an address, found '{}'", token)),
+            },
+        };
+        if !(0..=0xFFF).contains(&address) {
+            return self.error(format!("address {:#X} is out of range", address));
+        }
+        self.emit(opcode | address as u16)
+    }
+
+    fn condition(&mut self) -> Result<Condition, AsmError> {
+        let x = self.register()?;
+        let token = self.token()?;
+        match token {
+            "==" => Ok(Condition::Equal(x, self.operand()?)),
+            "!=" => Ok(Condition::NotEqual(x, self.operand()?)),
+            "key" => Ok(Condition::Key(x)),
+            "-key" => Ok(Condition::NotKey(x)),
+            _ => self.error(format!("unsupported condition '{}'", token)),
+        }
+    }
+
+    fn register_statement(&mut self, x: u8) -> Result<(), AsmError> {
+        let x_bits = (x as u16) << 8;
+        let operator = self.token()?;
+
+        // Operators that only take a register
+        let alu = match operator {
+            "|=" => Some(0x1),
+            "&=" => Some(0x2),
+            "^=" => Some(0x3),
+            ">>=" => Some(0x6),
+            "=-" => Some(0x7),
+            "<<=" => Some(0xE),
+            _ => None,
+        };
+        if let Some(alu) = alu {
+            let y = self.register()?;
+            return self.emit(0x8000 | x_bits | (y as u16) << 4 | alu);
+        }
+
+        match operator {
+            ":=" => match self.tokens.get(self.next).map(|&(token, _)| token) {
+                Some("random") => {
+                    self.token()?;
+                    let mask = self.byte()?;
+                    self.emit(0xC000 | x_bits | mask as u16)
+                }
+                Some("key") => {
+                    self.token()?;
+                    self.emit(0xF00A | x_bits)
+                }
+                Some("delay") => {
+                    self.token()?;
+                    self.emit(0xF007 | x_bits)
+                }
+                _ => match self.operand()? {
+                    Operand::Register(y) => self.emit(0x8000 | x_bits | (y as u16) << 4),
+                    Operand::Value(n) => self.emit(0x6000 | x_bits | n as u16),
+                },
+            },
+            "+=" => match self.operand()? {
+                Operand::Register(y) => self.emit(0x8004 | x_bits | (y as u16) << 4),
+                Operand::Value(n) => self.emit(0x7000 | x_bits | n as u16),
+            },
+            "-=" => match self.operand()? {
+                Operand::Register(y) => self.emit(0x8005 | x_bits | (y as u16) << 4),
+                Operand::Value(n) => self.emit(0x7000 | x_bits | n.wrapping_neg() as u16),
+            },
+            _ => self.error(format!("unknown operator '{}'", operator)),
+        }
+    }
+
+    fn statement(&mut self, token: &'a str) -> Result<(), AsmError> {
+        match token {
+            ":" => {
+                let name = self.token()?;
+                if self.labels.insert(name, self.address()).is_some() {
+                    return self.error(format!("label '{}' is defined twice", name));
+                }
+                Ok(())
+            }
+            ":const" => {
+                let name = self.token()?;
+                let value = self.value(-128, 0xFFF)?;
+                self.constants.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.token()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+                Ok(())
+            }
+            ":call" => self.emit_with_address(0x2000),
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit_byte(byte)
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "jump" => self.emit_with_address(0x1000),
+            "jump0" => self.emit_with_address(0xB000),
+            "i" => match self.token()? {
+                ":=" => {
+                    if self.tokens.get(self.next).map(|&(token, _)| token) == Some("hex") {
+                        self.token()?;
+                        let x = self.register()?;
+                        self.emit(0xF029 | (x as u16) << 8)
+                    } else {
+                        self.emit_with_address(0xA000)
+                    }
+                }
+                "+=" => {
+                    let x = self.register()?;
+                    self.emit(0xF01E | (x as u16) << 8)
+                }
+                operator => self.error(format!("unknown operator 'i {}'", operator)),
+            },
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let x = self.register()? as u16;
+                let opcode = if token == "delay" { 0xF015 } else { 0xF018 };
+                self.emit(opcode | x << 8)
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let height = self.value(0, 15)? as u16;
+                self.emit(0xD000 | x << 8 | y << 4 | height)
+            }
+            "bcd" | "save" | "load" => {
+                let x = self.register()? as u16;
+                let opcode = match token {
+                    "bcd" => 0xF033,
+                    "save" => 0xF055,
+                    _ => 0xF065,
+                };
+                self.emit(opcode | x << 8)
+            }
+            "if" => {
+                let condition = self.condition()?;
+                self.emit(condition.skip_unless())?;
+                match self.token()? {
+                    "then" => Ok(()),
+                    "begin" => {
+                        self.blocks.push(Block::If {
+                            jump: self.program.len(),
+                            line: self.line,
+                        });
+                        self.emit(0x1000)
+                    }
+                    token => self.error(format!("expected 'then' or 'begin', found '{}'", token)),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, line }) => {
+                    let end = self.program.len();
+                    self.emit(0x1000)?;
+                    self.patch(jump, self.address());
+                    self.blocks.push(Block::Else { jump: end, line });
+                    Ok(())
+                }
+                _ => self.error("'else' without 'if ... begin'".to_string()),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. } | Block::Else { jump, .. }) => {
+                    self.patch(jump, self.address());
+                    Ok(())
+                }
+                _ => self.error("'end' without 'if ... begin'".to_string()),
+            },
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    start: self.address(),
+                    exits: Vec::new(),
+                    line: self.line,
+                });
+                Ok(())
+            }
+            "while" => {
+                let condition = self.condition()?;
+                let Some(Block::Loop { .. }) = self.blocks.last() else {
+                    return self.error("'while' outside of a loop".to_string());
+                };
+                self.emit(condition.negate().skip_unless())?;
+                let offset = self.program.len();
+                self.emit(0x1000)?;
+                if let Some(Block::Loop { exits, .. }) = self.blocks.last_mut() {
+                    exits.push(offset);
+                }
+                Ok(())
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits, .. }) => {
+                    self.emit(0x1000 | start)?;
+                    for exit in exits {
+                        self.patch(exit, self.address());
+                    }
+                    Ok(())
+                }
+                _ => self.error("'again' without 'loop'".to_string()),
+            },
+            _ if token.starts_with(':') => self.error(format!("unsupported directive '{}'", token)),
+            _ => {
+                if let Some(x) = self.aliases.get(token).copied().or(parse_register(token)) {
+                    return self.register_statement(x);
+                }
+                if let Some(value) = self.number(token) {
+                    if !(-128..=255).contains(&value) {
+                        return self.error(format!("{} does not fit in a byte", value));
+                    }
+                    return self.emit_byte(value as u8);
+                }
+                // A bare label name calls it
+                self.next -= 1;
+                self.emit_with_address(0x2000)
+            }
+        }
+    }
+}
+
+// Whether `main` is defined somewhere other than at the start of the program
+fn needs_main_jump(tokens: &[(&str, usize)]) -> bool {
+    let defines_main = tokens
+        .windows(2)
+        .any(|pair| pair[0].0 == ":" && pair[1].0 == "main");
+    let mut rest = tokens;
+    while let [(":const" | ":alias", _), _, _, tail @ ..] = rest {
+        rest = tail;
+    }
+    defines_main && !matches!(rest, [(":", _), ("main", _), ..])
+}
+
+// Assembles the subset of Octo without macros or SUPER-CHIP/XO-CHIP
+// instructions. Execution starts at `main` if there is one.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    let tokens: Vec<(&str, usize)> = source
+        .lines()
+        .enumerate()
+        .flat_map(|(number, line)| {
+            let code = line.split('#').next().unwrap_or("");
+            code.split_whitespace()
+                .map(move |token| (token, number + 1))
+        })
+        .collect();
+
+    let mut assembler = Assembler {
+        tokens,
+        next: 0,
+        program: Vec::new(),
+        labels: HashMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+        line: 1,
+    };
+
+    if needs_main_jump(&assembler.tokens) {
+        assembler.fixups.push(Fixup {
+            offset: 0,
+            name: "main".to_string(),
+            line: 1,
+        });
+        assembler.emit(0x1000)?;
+    }
+
+    while assembler.next < assembler.tokens.len() {
+        let token = assembler.token()?;
+        assembler.statement(token)?;
+    }
+
+    if let Some(block) = assembler.blocks.last() {
+        let (line, name) = match block {
+            Block::If { line, .. } | Block::Else { line, .. } => (*line, "if ... begin"),
+            Block::Loop { line, .. } => (*line, "loop"),
+        };
+        return Err(AsmError {
+            line,
+            message: format!("'{}' is never closed", name),
+        });
+    }
+
+    for fixup in std::mem::take(&mut assembler.fixups) {
+        match assembler.labels.get(fixup.name.as_str()) {
+            Some(&address) => assembler.patch(fixup.offset, address),
+            None => {
+                return Err(AsmError {
+                    line: fixup.line,
+                    message: format!("undefined name '{}'", fixup.name),
+                });
+            }
+        }
+    }
+
+    Ok(assembler.program)
+}
@@ -0,0 +1,46 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::image::Image;
+
+pub mod recording;
+pub mod video;
+pub mod wav;
+
+pub fn save_png(image: &Image, path: &Path) -> io::Result<()> {
+    let file = File::create(path)?;
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(file),
+        image.width as u32,
+        image.height as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer
+        .write_image_data(&image.to_rgb_bytes())
+        .map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+// A file name that does not clash with earlier captures in `directory`
+pub fn timestamped_path(directory: &Path, prefix: &str, extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+
+    let mut path = directory.join(format!("{}_{}.{}", prefix, millis, extension));
+    let mut n = 1;
+    while path.exists() {
+        path = directory.join(format!("{}_{}_{}.{}", prefix, millis, n, extension));
+        n += 1;
+    }
+
+    path
+}
@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use crate::image::{Image, Rgb};
+
+const FPS: u64 = 60;
+
+// Longest a single frame is shown, about 8 minutes. Both formats store delays
+// in 16 bits, so longer stills are written as several frames.
+pub const MAX_FRAME_DELAY: u64 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Gif,
+    Apng,
+}
+
+impl RecordFormat {
+    // ".gif" records a GIF, ".png" and ".apng" an animated PNG
+    pub fn from_path(path: &Path) -> Option<RecordFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "gif" => Some(RecordFormat::Gif),
+            "png" | "apng" => Some(RecordFormat::Apng),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Gif => "gif",
+            RecordFormat::Apng => "png",
+        }
+    }
+}
+
+// GIF delays are in centiseconds, so they are derived from the absolute time
+// of each frame to keep the rounding error from adding up
+fn centiseconds(frame: u64) -> u64 {
+    (frame * 100 + FPS / 2) / FPS
+}
+
+// Records an animation at 60 frames per second. Only frames that differ from
+// the previous one are stored, with a delay covering the unchanged frames.
+pub struct Recorder {
+    path: PathBuf,
+    format: RecordFormat,
+    // Emulated frames since the recording started
+    frame: u64,
+    // Last captured image and the frame it was captured at
+    pending: Option<(Image, u64)>,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+    gif_time: u64,
+    apng_frames: Vec<(Image, u64)>,
+}
+
+impl Recorder {
+    pub fn new(path: impl Into<PathBuf>, format: RecordFormat) -> Self {
+        Recorder {
+            path: path.into(),
+            format,
+            frame: 0,
+            pending: None,
+            gif: None,
+            gif_time: 0,
+            apng_frames: Vec::new(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The picture shown during the current frame
+    pub fn capture(&mut self, image: &Image) -> io::Result<()> {
+        match self.pending.take() {
+            // Unchanged picture, it simply lasts longer
+            Some((last, start)) if last == *image => self.pending = Some((last, start)),
+            // Drawn again within the same frame, only the final picture counts
+            Some((_, start)) if start == self.frame => self.pending = Some((image.clone(), start)),
+            Some((last, start)) => {
+                self.write(last, self.frame - start)?;
+                self.pending = Some((image.clone(), self.frame));
+            }
+            None => self.pending = Some((image.clone(), self.frame)),
+        }
+
+        Ok(())
+    }
+
+    // Called once per emulated frame
+    pub fn tick(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some((last, start)) = self.pending.take() {
+            self.write(last, (self.frame - start).max(1))?;
+        }
+
+        match self.format {
+            RecordFormat::Gif => match self.gif.take() {
+                Some(encoder) => encoder.into_inner().map(|_| ()).map_err(io::Error::other),
+                None => Ok(()),
+            },
+            RecordFormat::Apng => self.write_apng(),
+        }
+    }
+
+    fn write(&mut self, image: Image, frames: u64) -> io::Result<()> {
+        let mut left = frames;
+        while left > MAX_FRAME_DELAY {
+            self.write_frame(image.clone(), MAX_FRAME_DELAY)?;
+            left -= MAX_FRAME_DELAY;
+        }
+        self.write_frame(image, left)
+    }
+
+    fn write_frame(&mut self, image: Image, frames: u64) -> io::Result<()> {
+        match self.format {
+            RecordFormat::Gif => self.write_gif_frame(&image, frames),
+            RecordFormat::Apng => {
+                self.apng_frames.push((image, frames));
+                Ok(())
+            }
+        }
+    }
+
+    fn write_gif_frame(&mut self, image: &Image, frames: u64) -> io::Result<()> {
+        if self.gif.is_none() {
+            let file = BufWriter::new(File::create(&self.path)?);
+            let mut encoder = gif::Encoder::new(file, image.width as u16, image.height as u16, &[])
+                .map_err(io::Error::other)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(io::Error::other)?;
+            self.gif = Some(encoder);
+        }
+
+        let end = self.gif_time + frames;
+        let delay = centiseconds(end) - centiseconds(self.gif_time);
+        self.gif_time = end;
+
+        let mut frame = match exact_palette(image) {
+            Some((palette, indices)) => gif::Frame::from_palette_pixels(
+                image.width as u16,
+                image.height as u16,
+                indices,
+                palette,
+                None,
+            ),
+            // Filters such as the CRT one produce too many colours
+            None => gif::Frame::from_rgb_speed(
+                image.width as u16,
+                image.height as u16,
+                &image.to_rgb_bytes(),
+                10,
+            ),
+        };
+        frame.delay = delay.max(1) as u16;
+
+        self.gif
+            .as_mut()
+            .unwrap()
+            .write_frame(&frame)
+            .map_err(io::Error::other)
+    }
+
+    fn write_apng(&mut self) -> io::Result<()> {
+        let Some((first, _)) = self.apng_frames.first() else {
+            return Ok(());
+        };
+
+        let file = BufWriter::new(File::create(&self.path)?);
+        let mut encoder = png::Encoder::new(file, first.width as u32, first.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .set_animated(self.apng_frames.len() as u32, 0)
+            .map_err(io::Error::other)?;
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        for (image, frames) in &self.apng_frames {
+            writer
+                .set_frame_delay(*frames as u16, FPS as u16)
+                .map_err(io::Error::other)?;
+            writer
+                .write_image_data(&image.to_rgb_bytes())
+                .map_err(io::Error::other)?;
+        }
+        writer.finish().map_err(io::Error::other)
+    }
+}
+
+// The palette of an image using at most 256 colours, with its indexed pixels
+fn exact_palette(image: &Image) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut colors: Vec<Rgb> = Vec::new();
+    let mut lookup: HashMap<Rgb, u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(image.pixels.len());
+
+    for pixel in &image.pixels {
+        let idx = match lookup.get(pixel) {
+            Some(idx) => *idx,
+            None if colors.len() < 256 => {
+                colors.push(*pixel);
+                lookup.insert(*pixel, (colors.len() - 1) as u8);
+                (colors.len() - 1) as u8
+            }
+            None => return None,
+        };
+        indices.push(idx);
+    }
+
+    let palette = colors.iter().flat_map(|c| [c.0, c.1, c.2]).collect();
+    Some((palette, indices))
+}
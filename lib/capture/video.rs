@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+
+use crate::image::{Image, Rgb};
+
+const FPS: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoFormat {
+    // YUV4MPEG2 with 4:4:4 chroma, carries its own size and frame rate
+    Y4m,
+    // Bare rgb24 frames, the reader has to be told the size and rate
+    Raw,
+}
+
+impl VideoFormat {
+    pub fn parse(name: &str) -> Result<VideoFormat, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "y4m" => Ok(VideoFormat::Y4m),
+            "raw" | "rgb" | "rgb24" => Ok(VideoFormat::Raw),
+            _ => Err(format!(
+                "unknown video format '{}', expected y4m or raw",
+                name
+            )),
+        }
+    }
+}
+
+// BT.601 limited range, what encoders assume for Y4M input
+fn to_yuv(Rgb(r, g, b): Rgb) -> (u8, u8, u8) {
+    let (r, g, b) = (r as f32, g as f32, b as f32);
+    let y = 16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0;
+    let u = 128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0;
+    let v = 128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0;
+    (y.round() as u8, u.round() as u8, v.round() as u8)
+}
+
+// Writes one picture per emulated frame, all frames must have the same size
+pub struct VideoWriter<W: Write> {
+    out: W,
+    format: VideoFormat,
+    size: Option<(usize, usize)>,
+    frames: u64,
+}
+
+impl<W: Write> VideoWriter<W> {
+    pub fn new(out: W, format: VideoFormat) -> Self {
+        VideoWriter {
+            out,
+            format,
+            size: None,
+            frames: 0,
+        }
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn write_frame(&mut self, image: &Image) -> io::Result<()> {
+        match self.size {
+            None => {
+                if self.format == VideoFormat::Y4m {
+                    writeln!(
+                        self.out,
+                        "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                        image.width, image.height, FPS
+                    )?;
+                }
+                self.size = Some((image.width, image.height));
+            }
+            Some(size) if size != (image.width, image.height) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the frame size changed during the video",
+                ));
+            }
+            Some(_) => {}
+        }
+
+        match self.format {
+            VideoFormat::Y4m => {
+                let pixels = image.pixels.len();
+                let mut planes = vec![0; pixels * 3];
+                for (i, &pixel) in image.pixels.iter().enumerate() {
+                    let (y, u, v) = to_yuv(pixel);
+                    planes[i] = y;
+                    planes[pixels + i] = u;
+                    planes[pixels * 2 + i] = v;
+                }
+                self.out.write_all(b"FRAME\n")?;
+                self.out.write_all(&planes)?;
+            }
+            VideoFormat::Raw => self.out.write_all(&image.to_rgb_bytes())?,
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
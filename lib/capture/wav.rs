@@ -0,0 +1,50 @@
+use std::io::{self, Seek, SeekFrom, Write};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+
+const HEADER_SIZE: u32 = 44;
+
+// Mono 16 bit PCM, the sizes in the header are filled in by `finish`
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    samples: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(&header(0))?;
+        Ok(WavWriter { out, samples: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.out.write_all(&bytes)?;
+        self.samples += samples.len() as u32;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(&header(self.samples * 2))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+fn header(data_size: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(HEADER_SIZE as usize);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(HEADER_SIZE - 8 + data_size).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    // PCM, mono
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    header.extend_from_slice(&2u16.to_le_bytes());
+    header.extend_from_slice(&16u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    header
+}
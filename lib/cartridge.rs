@@ -0,0 +1,300 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use serde_json::{Map, Value, json};
+
+use crate::assembler::{self, AsmError};
+use crate::chip::quirks::Quirks;
+use crate::chip::{INSTRUCTIONS_PER_FRAME, MAX_PROGRAM_SIZE};
+use crate::image::Rgb;
+use crate::palette::Palette;
+
+// Octo cartridges are GIFs carrying a JSON document with the program source
+// and the emulator options. The payload is the 32 bit big endian length of
+// the JSON text followed by the text itself, stored two bits per pixel in
+// the low bits of the palette indices, most significant bits first, running
+// through every frame in order. The upper bits of each index pick the colour
+// of the label drawn on top.
+pub const WIDTH: usize = 160;
+pub const HEIGHT: usize = 128;
+
+const BUZZ_COLOR: &str = "#FFAA00";
+const QUIET_COLOR: &str = "#000000";
+
+// What the cartridge sets besides the program itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartOptions {
+    pub tickrate: u32,
+    pub quirks: Quirks,
+    // None keeps the palette picked on the command line
+    pub palette: Option<Palette>,
+}
+
+impl Default for CartOptions {
+    fn default() -> Self {
+        CartOptions {
+            tickrate: INSTRUCTIONS_PER_FRAME,
+            quirks: Quirks::default(),
+            palette: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cartridge {
+    pub program: Vec<u8>,
+    pub options: CartOptions,
+}
+
+#[derive(Debug)]
+pub enum CartError {
+    Io(io::Error),
+    // Not an Octo cartridge, or a damaged one
+    Format(String),
+    // The program source does not assemble
+    Assembly(AsmError),
+}
+
+impl fmt::Display for CartError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CartError::Io(err) => write!(f, "{}", err),
+            CartError::Format(err) => write!(f, "{}", err),
+            CartError::Assembly(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for CartError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CartError::Io(err) => Some(err),
+            CartError::Assembly(err) => Some(err),
+            CartError::Format(_) => None,
+        }
+    }
+}
+
+// Octo booleans are sometimes stored as 0/1
+fn flag(options: &Map<String, Value>, key: &str) -> bool {
+    match options.get(key) {
+        Some(Value::Bool(b)) => *b,
+        Some(Value::Number(n)) => n.as_u64().is_some_and(|n| n != 0),
+        _ => false,
+    }
+}
+
+fn color(options: &Map<String, Value>, key: &str) -> Option<String> {
+    options.get(key)?.as_str().map(str::to_string)
+}
+
+// Octo source for `program` as a plain byte listing
+pub fn disassemble_bytes(program: &[u8]) -> String {
+    let mut source = String::from(": main\n");
+    for row in program.chunks(8) {
+        let bytes: Vec<String> = row.iter().map(|b| format!("0x{:02X}", b)).collect();
+        source.push_str(&format!("\t{}\n", bytes.join(" ")));
+    }
+    source
+}
+
+fn hex(color: Rgb) -> String {
+    format!("#{:02X}{:02X}{:02X}", color.0, color.1, color.2)
+}
+
+fn options_json(options: &CartOptions) -> Value {
+    let palette = options.palette.clone().unwrap_or_default();
+    let quirks = &options.quirks;
+
+    json!({
+        "tickrate": options.tickrate,
+        "backgroundColor": hex(palette.colors[0]),
+        "fillColor": hex(palette.colors[1]),
+        "fillColor2": hex(palette.colors[2]),
+        "blendColor": hex(palette.colors[3]),
+        "buzzColor": BUZZ_COLOR,
+        "quietColor": QUIET_COLOR,
+        "shiftQuirks": quirks.shift,
+        "loadStoreQuirks": quirks.load_store,
+        "vfOrderQuirks": false,
+        "clipQuirks": quirks.clip,
+        "vBlankQuirks": quirks.vblank,
+        "jumpQuirks": quirks.jump,
+        "logicQuirks": quirks.logic,
+        "screenRotation": 0,
+        "maxSize": MAX_PROGRAM_SIZE,
+        "touchInputMode": "none",
+        "fontStyle": "octo",
+    })
+}
+
+fn parse_options(options: &Map<String, Value>) -> Result<CartOptions, String> {
+    let tickrate = options
+        .get("tickrate")
+        .and_then(Value::as_u64)
+        .map_or(INSTRUCTIONS_PER_FRAME, |t| t as u32);
+
+    let quirks = Quirks {
+        shift: flag(options, "shiftQuirks"),
+        load_store: flag(options, "loadStoreQuirks"),
+        jump: flag(options, "jumpQuirks"),
+        logic: flag(options, "logicQuirks"),
+        clip: flag(options, "clipQuirks"),
+        vblank: flag(options, "vBlankQuirks"),
+    };
+
+    let colors: Vec<String> = ["backgroundColor", "fillColor", "fillColor2", "blendColor"]
+        .iter()
+        .map_while(|key| color(options, key))
+        .collect();
+    let palette = if colors.len() >= 2 {
+        let colors: Vec<&str> = colors.iter().map(String::as_str).collect();
+        let mut palette = Palette::from_hex_list(&colors)?;
+        palette.name = "cartridge".to_string();
+        Some(palette)
+    } else {
+        None
+    };
+
+    Ok(CartOptions {
+        tickrate,
+        quirks,
+        palette,
+    })
+}
+
+// The program source and the options stored in a cartridge
+fn read_document(gif_bytes: &[u8]) -> Result<(String, CartOptions), String> {
+    let mut decode_options = gif::DecodeOptions::new();
+    decode_options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = decode_options
+        .read_info(gif_bytes)
+        .map_err(|err| format!("not a GIF: {}", err))?;
+
+    let mut bits = Vec::new();
+    while let Some(frame) = decoder
+        .read_next_frame()
+        .map_err(|err| format!("broken GIF: {}", err))?
+    {
+        bits.extend(frame.buffer.iter().map(|index| index & 3));
+    }
+
+    let bytes: Vec<u8> = bits
+        .chunks_exact(4)
+        .map(|c| (c[0] << 6) | (c[1] << 4) | (c[2] << 2) | c[3])
+        .collect();
+
+    if bytes.len() < 4 {
+        return Err("not an Octo cartridge".to_string());
+    }
+    let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+    let text = bytes
+        .get(4..4 + size)
+        .and_then(|text| std::str::from_utf8(text).ok())
+        .ok_or("not an Octo cartridge")?;
+
+    let document: Value =
+        serde_json::from_str(text).map_err(|err| format!("broken cartridge data: {}", err))?;
+    let source = document
+        .get("program")
+        .and_then(Value::as_str)
+        .ok_or("the cartridge has no program")?;
+    let options = match document.get("options") {
+        Some(Value::Object(options)) => parse_options(options)?,
+        _ => CartOptions::default(),
+    };
+
+    Ok((source.to_string(), options))
+}
+
+// Octo stores the program as source code, which is assembled here. Only the
+// subset `assembler` knows is accepted: no macros, no `:calc` and no
+// SUPER-CHIP or XO-CHIP instructions, so most published cartridges fail with
+// `CartError::Assembly`.
+pub fn decode(gif_bytes: &[u8]) -> Result<Cartridge, CartError> {
+    let (source, options) = read_document(gif_bytes).map_err(CartError::Format)?;
+    Ok(Cartridge {
+        program: assembler::assemble(&source).map_err(CartError::Assembly)?,
+        options,
+    })
+}
+
+pub fn load(path: &Path) -> Result<Cartridge, CartError> {
+    decode(&fs::read(path).map_err(CartError::Io)?)
+}
+
+// Base colour of every pixel of the label, 0 to 3
+fn label() -> Vec<u8> {
+    let mut pixels = vec![0; WIDTH * HEIGHT];
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let border = !(8..WIDTH - 8).contains(&x) || y < 8;
+            let sticker = (20..WIDTH - 20).contains(&x) && (20..HEIGHT - 36).contains(&y);
+            let pins = y >= HEIGHT - 16 && x % 12 < 6 && (16..WIDTH - 16).contains(&x);
+
+            pixels[y * WIDTH + x] = if sticker {
+                2
+            } else if border || pins {
+                1
+            } else {
+                0
+            };
+        }
+    }
+
+    pixels
+}
+
+pub fn encode(cartridge: &Cartridge) -> Result<Vec<u8>, String> {
+    let document = json!({
+        "options": options_json(&cartridge.options),
+        "program": disassemble_bytes(&cartridge.program),
+    });
+    let text = document.to_string();
+
+    let mut payload = (text.len() as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(text.as_bytes());
+    let bits: Vec<u8> = payload
+        .iter()
+        .flat_map(|b| [b >> 6, (b >> 4) & 3, (b >> 2) & 3, b & 3])
+        .collect();
+
+    // Every pixel index is base * 4 + bits, the bits only nudge the colour
+    let palette = cartridge.options.palette.clone().unwrap_or_default();
+    let mut global_palette = Vec::new();
+    for base in palette.colors {
+        for bits in 0..4u8 {
+            global_palette.extend([base.0 ^ bits, base.1 ^ bits, base.2 ^ bits]);
+        }
+    }
+
+    let label = label();
+    let mut out = Vec::new();
+    {
+        let mut encoder = gif::Encoder::new(&mut out, WIDTH as u16, HEIGHT as u16, &global_palette)
+            .map_err(|err| err.to_string())?;
+
+        for chunk in bits.chunks(WIDTH * HEIGHT) {
+            let indices: Vec<u8> = label
+                .iter()
+                .enumerate()
+                .map(|(i, base)| base * 4 + chunk.get(i).copied().unwrap_or(0))
+                .collect();
+
+            let frame = gif::Frame {
+                width: WIDTH as u16,
+                height: HEIGHT as u16,
+                buffer: indices.into(),
+                ..gif::Frame::default()
+            };
+            encoder.write_frame(&frame).map_err(|err| err.to_string())?;
+        }
+    }
+
+    Ok(out)
+}
+
+pub fn save(cartridge: &Cartridge, path: &Path) -> Result<(), String> {
+    let bytes = encode(cartridge)?;
+    fs::write(path, bytes).map_err(|err| format!("Cannot write {}: {}", path.display(), err))
+}
@@ -1,23 +1,69 @@
 use std::{
-    collections::HashMap, fs::File, io::Read, panic::panic_any, process::exit, time::Duration,
+    collections::HashMap,
+    fmt,
+    io::{self, Read},
+    panic::panic_any,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
 };
 
-use rand::Rng;
-use sdl2::{event::Event, keyboard::Keycode, pixels::Color};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 
-use crate::display;
+use crate::cartridge::{self, Cartridge};
+use crate::database::{Database, RomInfo};
+use crate::display::{self, Framebuffer};
+use crate::frontend::{Action, AudioSink, InputSource, KeypadState, Renderer};
+use crate::watch::FileWatcher;
 
+pub mod playback;
+pub mod quirks;
+pub mod rom;
 mod stack;
 
-enum InstrVersion {
-    Old,
-    New,
-}
+use playback::Playback;
+use quirks::Quirks;
+use rom::LoadError;
 
 const RAM_SIZE: usize = 4096;
 const PROGRAM_START: u16 = 512;
+pub const MAX_PROGRAM_SIZE: usize = RAM_SIZE - PROGRAM_START as usize;
+
+// Why `Chip::interpret` stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunOutcome {
+    Menu,
+    Quit,
+    // The user asked for another ROM, such as by dropping it on the window
+    Open(PathBuf),
+}
+
+// Why a program cannot go on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    UnknownInstruction(u16),
+    // 00EE without a subroutine to return from
+    EmptyStack,
+    // An instruction or the memory at the index register past the end of
+    // the memory
+    OutOfMemory(u16),
+}
 
-const OP_8XY6_VERSION: InstrVersion = InstrVersion::Old;
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UnknownInstruction(istr) => {
+                write!(f, "Instruction {:#06x} do not exists!", istr)
+            }
+            Fault::EmptyStack => write!(f, "Return without a subroutine call"),
+            Fault::OutOfMemory(addr) => write!(f, "Address {:#06x} is out of memory", addr),
+        }
+    }
+}
+
+// 720 instructions/sec, so timers decrease once every 12 instructions
+pub const INSTRUCTIONS_PER_FRAME: u32 = 12;
+pub const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
 
 fn initialize_registers() -> HashMap<u8, u8> {
     let mut hm: HashMap<u8, u8> = HashMap::new();
@@ -42,12 +88,6 @@ fn initialize_registers() -> HashMap<u8, u8> {
     hm
 }
 
-#[derive(Debug, PartialEq)]
-enum MetaInputs {
-    PressedInput,
-    Pass,
-}
-
 pub struct Chip {
     // Only 12 bits are used in program_counter and index_register
     program_counter: u16,
@@ -57,10 +97,24 @@ pub struct Chip {
     // stack of addresses of 12 bits, represented as 16 bits
     stack: stack::Stack<u16>,
     delay_timer: u8,
-    num_instructions: u32,
     sound_timer: u8,
     screen: display::Display,
-    key_pressed: Option<u8>,
+    keypad: KeypadState,
+    quirks: Quirks,
+    // Instructions executed per 60 Hz frame
+    tickrate: u32,
+    // Set by DXYN with the vblank quirk to end the frame early
+    waiting_vblank: bool,
+    program_size: usize,
+    rom_info: Option<RomInfo>,
+    rng: StdRng,
+    seed: Option<u64>,
+    playback: Playback,
+    // The program as loaded and the file it came from, for resets
+    rom: Vec<u8>,
+    rom_path: Option<PathBuf>,
+    // Reloads the ROM when its file changes
+    watcher: Option<FileWatcher>,
 }
 
 fn initialize_font() -> [u8; 4096] {
@@ -97,26 +151,218 @@ impl Chip {
             memory: initialize_font(),
             stack: stack::Stack::new(),
             delay_timer: 0,
-            num_instructions: 0,
             sound_timer: 0,
             screen: display::Display::new(),
-            key_pressed: None,
+            keypad: KeypadState::default(),
+            quirks: Quirks::default(),
+            tickrate: INSTRUCTIONS_PER_FRAME,
+            waiting_vblank: false,
+            program_size: 0,
+            rom_info: None,
+            rng: StdRng::from_os_rng(),
+            seed: None,
+            playback: Playback::default(),
+            rom: Vec::new(),
+            rom_path: None,
+            watcher: None,
+        }
+    }
+
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.screen
+    }
+
+    // Memory where the ROM was loaded, running programs may change it
+    pub fn program(&self) -> &[u8] {
+        let start = PROGRAM_START as usize;
+        &self.memory[start..start + self.program_size]
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn tickrate(&self) -> u32 {
+        self.tickrate
+    }
+
+    pub fn set_tickrate(&mut self, tickrate: u32) {
+        self.tickrate = tickrate.max(1);
+    }
+
+    // Makes CXNN return the same numbers on every run
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.seed = Some(seed);
+    }
+
+    // Pause, speed and their rates while interpreting
+    pub fn playback(&self) -> &Playback {
+        &self.playback
+    }
+
+    pub fn playback_mut(&mut self) -> &mut Playback {
+        &mut self.playback
+    }
+
+    // Loads a ROM file. "-" reads stdin, .gif files are Octo cartridges and
+    // bring their quirks and tickrate along, .8o files are assembled and
+    // .hex files are hex listings.
+    // Other ROMs get their settings from the bundled database if it knows
+    // them.
+    pub fn load_program(&mut self, filename: &str) -> Result<(), LoadError> {
+        let lowercase = filename.to_ascii_lowercase();
+
+        if lowercase.ends_with(".gif") {
+            let path = Path::new(filename);
+            let cartridge = cartridge::load(path)?;
+            self.load_cartridge(&cartridge)?;
+            if let (Some(info), Some(stem)) = (&mut self.rom_info, path.file_stem()) {
+                info.title = stem.to_string_lossy().into_owned();
+            }
+            self.rom_path = Some(path.to_path_buf());
+            return Ok(());
         }
+
+        if filename == "-" {
+            self.load_reader(io::stdin().lock())?;
+        } else {
+            self.load_bytes(&rom::read(Path::new(filename))?)?;
+        }
+        self.rom_path = (filename != "-").then(|| PathBuf::from(filename));
+
+        if let Some(info) = Database::bundled().lookup(self.program()) {
+            self.apply_rom_info(info.clone());
+        }
+        Ok(())
+    }
+
+    pub fn apply_rom_info(&mut self, info: RomInfo) {
+        if let Some(quirks) = info.quirks {
+            self.set_quirks(quirks);
+        }
+        if let Some(tickrate) = info.tickrate {
+            self.set_tickrate(tickrate);
+        }
+        self.rom_info = Some(info);
+    }
+
+    // What the ROM database knows about the loaded program
+    pub fn rom_info(&self) -> Option<&RomInfo> {
+        self.rom_info.as_ref()
     }
 
-    pub fn load_program(&mut self, filename: &str) {
-        let mut f = File::open(filename)
-            .expect(format!("Error: Cannot load this file: {}", filename).as_str());
+    pub fn load_cartridge(&mut self, cartridge: &Cartridge) -> Result<(), LoadError> {
+        self.load_bytes(&cartridge.program)?;
+        self.apply_rom_info(RomInfo {
+            title: "Octo cartridge".to_string(),
+            authors: Vec::new(),
+            platform: None,
+            quirks: Some(cartridge.options.quirks),
+            tickrate: Some(cartridge.options.tickrate),
+            palette: cartridge.options.palette.clone(),
+            keys: Vec::new(),
+        });
+        Ok(())
+    }
 
+    pub fn load_reader(&mut self, mut reader: impl Read) -> Result<(), LoadError> {
         let mut buf: Vec<u8> = vec![];
+        reader.read_to_end(&mut buf)?;
+        self.load_bytes(&buf)
+    }
 
-        File::read_to_end(&mut f, &mut buf).unwrap();
+    pub fn load_hex(&mut self, text: &str) -> Result<(), LoadError> {
+        self.load_bytes(&rom::parse_hex(text)?)
+    }
 
-        for (i, val) in buf.iter().enumerate() {
-            self.memory[PROGRAM_START as usize + i] = *val;
+    pub fn load_bytes(&mut self, program: &[u8]) -> Result<(), LoadError> {
+        if program.len() > MAX_PROGRAM_SIZE {
+            return Err(LoadError::TooLarge {
+                size: program.len(),
+                max: MAX_PROGRAM_SIZE,
+            });
         }
 
+        let start = PROGRAM_START as usize;
+        self.memory[start..start + program.len()].copy_from_slice(program);
         self.program_counter = PROGRAM_START;
+        self.program_size = program.len();
+        self.rom = program.to_vec();
+
+        Ok(())
+    }
+
+    // The file the ROM was loaded from, None for stdin and bytes
+    pub fn rom_path(&self) -> Option<&Path> {
+        self.rom_path.as_deref()
+    }
+
+    // Back to how the machine was right after loading: registers, timers,
+    // stack, screen and memory are cleared and the ROM is read again from
+    // its file, so that a rebuilt ROM takes effect. Quirks, tickrate, seed
+    // and playback settings are kept.
+    pub fn reset(&mut self) -> Result<(), LoadError> {
+        let program = match &self.rom_path {
+            Some(path) => rom::read(path)?,
+            None => self.rom.clone(),
+        };
+
+        let mut fresh = Chip {
+            quirks: self.quirks,
+            tickrate: self.tickrate,
+            rom_info: self.rom_info.clone(),
+            playback: self.playback.clone(),
+            rom_path: self.rom_path.clone(),
+            watcher: self.watcher.clone(),
+            ..Chip::new()
+        };
+        if let Some(seed) = self.seed {
+            fresh.set_seed(seed);
+        }
+        // A ROM that no longer loads leaves the old one running
+        fresh.load_bytes(&program)?;
+
+        *self = fresh;
+        Ok(())
+    }
+
+    // Swaps a new version of the program into memory while it runs, keeping
+    // registers, timers, stack and screen. The program restarts if it was
+    // running past the end of the new version.
+    pub fn hot_swap(&mut self, program: &[u8]) -> Result<(), LoadError> {
+        if program.len() > MAX_PROGRAM_SIZE {
+            return Err(LoadError::TooLarge {
+                size: program.len(),
+                max: MAX_PROGRAM_SIZE,
+            });
+        }
+
+        // Leftovers of a longer old version would still run as code
+        let start = PROGRAM_START as usize;
+        let old_end = start + self.program_size;
+        self.memory[start..old_end].fill(0);
+        self.memory[start..start + program.len()].copy_from_slice(program);
+        self.program_size = program.len();
+        self.rom = program.to_vec();
+
+        if self.program_counter as usize >= start + program.len() {
+            self.program_counter = PROGRAM_START;
+        }
+        Ok(())
+    }
+
+    // Reloads the ROM whenever its file changes, for ROMs that have one.
+    // Octo sources are swapped in while running, other ROMs restart.
+    pub fn set_watch(&mut self, watch: bool) {
+        self.watcher = match &self.rom_path {
+            Some(path) if watch => Some(FileWatcher::new(path)),
+            _ => None,
+        };
     }
 
     fn fetch(&mut self) -> u16 {
@@ -195,18 +441,30 @@ impl Chip {
     fn binary_or(&mut self, second_nibble: u8, third_nibble: u8) {
         *self.registers.get_mut(&second_nibble).unwrap() |=
             *self.registers.get(&third_nibble).unwrap();
+
+        if self.quirks.logic {
+            *self.registers.get_mut(&0xF).unwrap() = 0;
+        }
     }
 
     // opcode: 8XY2
     fn binary_and(&mut self, second_nibble: u8, third_nibble: u8) {
         *self.registers.get_mut(&second_nibble).unwrap() &=
             *self.registers.get(&third_nibble).unwrap();
+
+        if self.quirks.logic {
+            *self.registers.get_mut(&0xF).unwrap() = 0;
+        }
     }
 
     // opcode: 8XY3
     fn logical_xor(&mut self, second_nibble: u8, third_nibble: u8) {
         *self.registers.get_mut(&second_nibble).unwrap() ^=
             *self.registers.get(&third_nibble).unwrap();
+
+        if self.quirks.logic {
+            *self.registers.get_mut(&0xF).unwrap() = 0;
+        }
     }
 
     // opcode: 8XY4
@@ -232,59 +490,49 @@ impl Chip {
         *self.registers.get_mut(&0xF).unwrap() = new_vf;
     }
 
-    // TODO: Far configurare dall'utente se non
-    //       vuole questa config: "Set VX to the value of VY"
-
     // opcode: 8XY6
     fn shift_right(&mut self, second_nibble: u8, third_nibble: u8) {
-        match OP_8XY6_VERSION {
-            InstrVersion::Old => {
-                *self.registers.get_mut(&second_nibble).unwrap() =
-                    *self.registers.get(&third_nibble).unwrap();
-
-                let shifted_value = *self.registers.get(&second_nibble).unwrap() & 0b00000001;
-                *self.registers.get_mut(&second_nibble).unwrap() =
-                    *self.registers.get(&second_nibble).unwrap() >> 1;
-
-                if shifted_value == 1 {
-                    *self.registers.get_mut(&0xF).unwrap() = 1;
-                } else {
-                    *self.registers.get_mut(&0xF).unwrap() = 0;
-                }
+        if !self.quirks.shift {
+            *self.registers.get_mut(&second_nibble).unwrap() =
+                *self.registers.get(&third_nibble).unwrap();
+
+            let shifted_value = *self.registers.get(&second_nibble).unwrap() & 0b00000001;
+            *self.registers.get_mut(&second_nibble).unwrap() =
+                *self.registers.get(&second_nibble).unwrap() >> 1;
+
+            if shifted_value == 1 {
+                *self.registers.get_mut(&0xF).unwrap() = 1;
+            } else {
+                *self.registers.get_mut(&0xF).unwrap() = 0;
             }
-            InstrVersion::New => {
-                *self.registers.get_mut(&0xF).unwrap() =
-                    *self.registers.get(&second_nibble).unwrap() & 1;
+        } else {
+            *self.registers.get_mut(&0xF).unwrap() =
+                *self.registers.get(&second_nibble).unwrap() & 1;
 
-                *self.registers.get_mut(&second_nibble).unwrap() >>= 1;
-            }
+            *self.registers.get_mut(&second_nibble).unwrap() >>= 1;
         }
     }
 
     // opcode: 8XYE
     fn shift_left(&mut self, second_nibble: u8, third_nibble: u8) {
-        match OP_8XY6_VERSION {
-            InstrVersion::Old => {
-                *self.registers.get_mut(&second_nibble).unwrap() =
-                    *self.registers.get(&third_nibble).unwrap();
-
-                let shifted_value =
-                    (*self.registers.get(&second_nibble).unwrap() & 0b10000000) >> 7;
-                *self.registers.get_mut(&second_nibble).unwrap() =
-                    *self.registers.get(&second_nibble).unwrap() << 1;
-
-                if shifted_value == 1 {
-                    *self.registers.get_mut(&0xF).unwrap() = 1;
-                } else {
-                    *self.registers.get_mut(&0xF).unwrap() = 0;
-                }
+        if !self.quirks.shift {
+            *self.registers.get_mut(&second_nibble).unwrap() =
+                *self.registers.get(&third_nibble).unwrap();
+
+            let shifted_value = (*self.registers.get(&second_nibble).unwrap() & 0b10000000) >> 7;
+            *self.registers.get_mut(&second_nibble).unwrap() =
+                *self.registers.get(&second_nibble).unwrap() << 1;
+
+            if shifted_value == 1 {
+                *self.registers.get_mut(&0xF).unwrap() = 1;
+            } else {
+                *self.registers.get_mut(&0xF).unwrap() = 0;
             }
-            InstrVersion::New => {
-                *self.registers.get_mut(&0xF).unwrap() =
-                    (*self.registers.get(&second_nibble).unwrap() & 0b10000000) >> 7;
+        } else {
+            *self.registers.get_mut(&0xF).unwrap() =
+                (*self.registers.get(&second_nibble).unwrap() & 0b10000000) >> 7;
 
-                *self.registers.get_mut(&second_nibble).unwrap() <<= 1;
-            }
+            *self.registers.get_mut(&second_nibble).unwrap() <<= 1;
         }
     }
 
@@ -316,17 +564,20 @@ impl Chip {
         self.index_register = nnn;
     }
 
-    // TODO: Implemented the classic behaviour: make this configurable
-    //       to work as BXNN -> jump to the address XNN + register VX
-
     // opcode: BNNN
     fn jump_with_offset(&mut self, nnn: u16) {
-        self.program_counter = nnn + *self.registers.get(&0x0).unwrap() as u16;
+        // With the jump quirk this is BXNN, X selects the offset register
+        let register = if self.quirks.jump {
+            (nnn >> 8) as u8
+        } else {
+            0x0
+        };
+        self.program_counter = nnn + *self.registers.get(&register).unwrap() as u16;
     }
 
     // opcode: CXNN
     fn random(&mut self, second_nibble: u8, nn: u8) {
-        let random: u8 = rand::random();
+        let random: u8 = self.rng.random();
         *self.registers.get_mut(&second_nibble).unwrap() = random & nn;
     }
 
@@ -345,8 +596,14 @@ impl Chip {
 
             for x_line in 0..8 {
                 if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                    let x = (x_coord as usize + x_line) % 64;
-                    let y = (y_coord as usize + y_line as usize) % 32;
+                    // The sprite origin always wraps, the rest of it only
+                    // without the clip quirk
+                    let x = x_coord as usize % 64 + x_line;
+                    let y = y_coord as usize % 32 + y_line as usize;
+                    if self.quirks.clip && (x >= 64 || y >= 32) {
+                        continue;
+                    }
+                    let (x, y) = (x % 64, y % 32);
 
                     let idx = x + 64 * y;
                     flipped |= self.screen.data[idx];
@@ -362,19 +619,15 @@ impl Chip {
         }
 
         self.screen.redraw = true;
+        self.waiting_vblank = self.quirks.vblank;
     }
 
     // opcode: EX9E
     fn skip_if_key_pressed(&mut self, second_nibble: u8) {
         let key = *self.registers.get(&second_nibble).unwrap();
 
-        match self.key_pressed {
-            Some(a) => {
-                if key == a {
-                    self.program_counter += 2;
-                }
-            }
-            None => {}
+        if self.keypad.is_pressed(key) {
+            self.program_counter += 2;
         }
     }
 
@@ -382,15 +635,8 @@ impl Chip {
     fn skip_if_key_not_pressed(&mut self, second_nibble: u8) {
         let key = *self.registers.get(&second_nibble).unwrap();
 
-        match self.key_pressed {
-            Some(a) => {
-                if key != a {
-                    self.program_counter += 2;
-                }
-            }
-            None => {
-                self.program_counter += 2;
-            }
+        if !self.keypad.is_pressed(key) {
+            self.program_counter += 2;
         }
     }
 
@@ -401,12 +647,10 @@ impl Chip {
 
     // opcode: FX0A
     fn get_key(&mut self, second_nibble: u8) {
-        loop {
-            _ = self.poll_inputs();
-            if self.key_pressed.is_some() {
-                *self.registers.get_mut(&second_nibble).unwrap() = self.key_pressed.unwrap();
-                break;
-            }
+        // Blocks by executing this instruction again until a key is pressed
+        match self.keypad.first_pressed() {
+            Some(key) => *self.registers.get_mut(&second_nibble).unwrap() = key,
+            None => self.program_counter -= 2,
         }
     }
 
@@ -422,7 +666,9 @@ impl Chip {
 
     // opcode: FX1E
     fn add_to_index(&mut self, second_nibble: u8) {
-        self.index_register += *self.registers.get(&second_nibble).unwrap() as u16;
+        self.index_register = self
+            .index_register
+            .wrapping_add(*self.registers.get(&second_nibble).unwrap() as u16);
         *self.registers.get_mut(&0xF).unwrap() = if self.index_register > 0x0F00 { 1 } else { 0 };
     }
 
@@ -439,15 +685,15 @@ impl Chip {
         self.memory[self.index_register as usize] = num / 100;
     }
 
-    // TODO: Implement the user choice for old behaviour of this instruction
-
     // opcode: FX55
     fn store_memory(&mut self, second_nibble: u8) {
         for reg in 0..=second_nibble {
             self.memory[(self.index_register + reg as u16) as usize] =
                 *self.registers.get(&reg).unwrap();
         }
-        self.index_register += second_nibble as u16 + 1;
+        if !self.quirks.load_store {
+            self.index_register += second_nibble as u16 + 1;
+        }
     }
 
     // opcode: FX65
@@ -456,12 +702,33 @@ impl Chip {
             *self.registers.get_mut(&reg).unwrap() =
                 self.memory[(self.index_register + reg as u16) as usize];
         }
-        self.index_register += second_nibble as u16 + 1;
+        if !self.quirks.load_store {
+            self.index_register += second_nibble as u16 + 1;
+        }
     }
 
+    // Runs one instruction, panicking on the ones that cannot run
     pub fn instruction(&mut self) {
+        if let Err(fault) = self.try_instruction() {
+            panic_any(format!("Error: {}", fault));
+        }
+    }
+
+    // Runs one instruction, or leaves the machine as it was when the program
+    // cannot go on
+    pub fn try_instruction(&mut self) -> Result<(), Fault> {
+        if self.program_counter as usize + 1 >= RAM_SIZE {
+            return Err(Fault::OutOfMemory(self.program_counter));
+        }
         let istr = self.fetch();
+        let result = self.execute(istr);
+        if result.is_err() {
+            self.program_counter -= 2;
+        }
+        result
+    }
 
+    fn execute(&mut self, istr: u16) -> Result<(), Fault> {
         // Extract nibbles
         let first_nibble: u8 = (istr >> 12) as u8;
         let second_nibble: u8 = ((istr >> 8) & 0x000F) as u8;
@@ -471,17 +738,32 @@ impl Chip {
         let nn: u8 = (istr & 0x00FF) as u8;
         let nnn: u16 = istr & 0x0FFF;
 
+        let unknown = Fault::UnknownInstruction(istr);
+        // Memory the instruction reads or writes from the index register on
+        let memory = match (first_nibble, nn) {
+            (0xD, _) => fourth_nibble as usize,
+            (0xF, 0x33) => 3,
+            (0xF, 0x55 | 0x65) => second_nibble as usize + 1,
+            _ => 0,
+        };
+        if self.index_register as usize + memory > RAM_SIZE {
+            return Err(Fault::OutOfMemory(self.index_register));
+        }
+        if istr == 0x00EE && self.stack.is_empty() {
+            return Err(Fault::EmptyStack);
+        }
+
         match first_nibble {
             0x0 => match second_nibble {
                 0x0 => match third_nibble {
                     0xE => match fourth_nibble {
                         0x0 => self.clear_screen(),
                         0xE => self.subroutine_return(),
-                        _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                        _ => return Err(unknown),
                     },
-                    _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                    _ => return Err(unknown),
                 },
-                _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                _ => return Err(unknown),
             },
             0x1 => self.jump(nnn),
             0x2 => self.subroutine_call(nnn),
@@ -500,7 +782,7 @@ impl Chip {
                 0x6 => self.shift_right(second_nibble, third_nibble),
                 0x7 => self.subtract_vy(second_nibble, third_nibble),
                 0xE => self.shift_left(second_nibble, third_nibble),
-                _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                _ => return Err(unknown),
             },
             0x9 => self.skip_not_equal_binary(second_nibble, third_nibble),
             0xA => self.set_index(nnn),
@@ -511,151 +793,191 @@ impl Chip {
                 0x9 | 0xA => match fourth_nibble {
                     0x1 => self.skip_if_key_not_pressed(second_nibble),
                     0xE => self.skip_if_key_pressed(second_nibble),
-                    _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                    _ => return Err(unknown),
                 },
-                _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                _ => return Err(unknown),
             },
             0xF => match third_nibble {
                 0x0 => match fourth_nibble {
                     0x7 => self.set_reg_to_delay(second_nibble),
                     0xA => self.get_key(second_nibble),
-                    _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                    _ => return Err(unknown),
                 },
                 0x1 => match fourth_nibble {
                     0x5 => self.set_delay_to_reg(second_nibble),
                     0x8 => self.set_sound_to_vx(second_nibble),
                     0xE => self.add_to_index(second_nibble),
-                    _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                    _ => return Err(unknown),
                 },
                 0x2 => self.font_character(second_nibble),
                 0x3 => self.binary_coded_dec_conv(second_nibble),
                 0x5 => self.store_memory(second_nibble),
                 0x6 => self.load_memory(second_nibble),
-                _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+                _ => return Err(unknown),
             },
-            _ => panic_any(format!("Error: Instruction {:#06x} do not exists!", istr)),
+            _ => return Err(unknown),
         }
+        Ok(())
     }
 
-    fn poll_inputs(&mut self) -> MetaInputs {
-        for event in self.screen.event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => {
-                    exit(0);
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Num1),
-                    ..
-                } => self.key_pressed = Some(0x1),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Num2),
-                    ..
-                } => self.key_pressed = Some(0x2),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Num3),
-                    ..
-                } => self.key_pressed = Some(0x3),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Num4),
-                    ..
-                } => self.key_pressed = Some(0xC),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Q),
-                    ..
-                } => self.key_pressed = Some(0x4),
-                Event::KeyDown {
-                    keycode: Some(Keycode::W),
-                    ..
-                } => self.key_pressed = Some(0x5),
-                Event::KeyDown {
-                    keycode: Some(Keycode::E),
-                    ..
-                } => self.key_pressed = Some(0x6),
-                Event::KeyDown {
-                    keycode: Some(Keycode::R),
-                    ..
-                } => self.key_pressed = Some(0xD),
-                Event::KeyDown {
-                    keycode: Some(Keycode::A),
-                    ..
-                } => self.key_pressed = Some(0x7),
-                Event::KeyDown {
-                    keycode: Some(Keycode::S),
-                    ..
-                } => self.key_pressed = Some(0x8),
-                Event::KeyDown {
-                    keycode: Some(Keycode::D),
-                    ..
-                } => self.key_pressed = Some(0x9),
-                Event::KeyDown {
-                    keycode: Some(Keycode::F),
-                    ..
-                } => self.key_pressed = Some(0xE),
-                Event::KeyDown {
-                    keycode: Some(Keycode::Z),
-                    ..
-                } => self.key_pressed = Some(0xA),
-                Event::KeyDown {
-                    keycode: Some(Keycode::X),
-                    ..
-                } => self.key_pressed = Some(0x0),
-                Event::KeyDown {
-                    keycode: Some(Keycode::C),
-                    ..
-                } => self.key_pressed = Some(0xB),
-                Event::KeyDown {
-                    keycode: Some(Keycode::V),
-                    ..
-                } => self.key_pressed = Some(0xF),
-                Event::KeyUp { .. } => {
-                    self.key_pressed = None;
-                }
-                _ => return MetaInputs::Pass,
-            }
-            return MetaInputs::PressedInput;
+    // Executes one 60 Hz frame worth of instructions and ticks the timers
+    pub fn run_frame(&mut self, keypad: KeypadState) {
+        if let Err(fault) = self.try_run_frame(keypad) {
+            panic_any(format!("Error: {}", fault));
         }
-
-        return MetaInputs::Pass;
     }
 
-    pub fn interpret(&mut self) {
-        loop {
-            if self.screen.redraw {
-                self.screen.redraw = false;
-                self.screen.canvas.set_draw_color(Color::BLACK);
-                self.screen.canvas.clear();
-
-                self.screen.canvas.set_draw_color(Color::WHITE);
-                self.screen
-                    .canvas
-                    .fill_rects(&self.screen.create_white_rects())
-                    .expect("error printing the squares");
-
-                self.screen.canvas.present();
+    // Like `run_frame`, stopping at the first instruction that cannot run
+    pub fn try_run_frame(&mut self, keypad: KeypadState) -> Result<(), Fault> {
+        self.keypad = keypad;
+
+        for _ in 0..self.tickrate {
+            self.try_instruction()?;
+            if self.waiting_vblank {
+                self.waiting_vblank = false;
+                break;
             }
+        }
 
-            _ = self.poll_inputs();
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+        Ok(())
+    }
+
+    // One frame of emulation wired to a frontend, without any pacing. The
+    // screen is still shown when the program stops on a fault.
+    pub fn step<R: Renderer, I: InputSource, A: AudioSink>(
+        &mut self,
+        renderer: &mut R,
+        input: &mut I,
+        audio: &mut A,
+    ) -> Result<(), Fault> {
+        let result = self.try_run_frame(input.poll());
+        audio.set_playing(self.sound_timer > 0);
+
+        if self.screen.redraw {
+            self.screen.redraw = false;
+            renderer.present(&self.screen);
+        } else if renderer.needs_redraw() {
+            renderer.redraw();
+        }
 
-            if self.num_instructions == 12 {
-                if self.delay_timer > 0 {
-                    self.delay_timer -= 1;
+        renderer.end_frame();
+        result
+    }
+
+    // Reloads the ROM from the run loop, where errors can only be shown.
+    // With `restart` unset Octo sources are hot swapped instead of reset.
+    fn reload<R: Renderer>(&mut self, renderer: &mut R, restart: bool) {
+        let source = self
+            .rom_path
+            .as_ref()
+            .filter(|path| path.extension().is_some_and(|ext| ext == "8o"));
+        let result = match source {
+            Some(path) if !restart => rom::read(path).and_then(|program| self.hot_swap(&program)),
+            _ => self.reset(),
+        };
+
+        match result {
+            Ok(()) => {
+                renderer.set_error(None);
+                match (&self.rom_path, restart) {
+                    (Some(path), false) => {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        renderer.show_message(&format!("Reloaded {}", name));
+                    }
+                    _ => renderer.show_message("Reset"),
                 }
-                self.num_instructions = 0;
             }
+            Err(err) => {
+                let message = match &self.rom_path {
+                    Some(path) => format!("{}: {}", path.display(), err),
+                    None => err.to_string(),
+                };
+                renderer.set_error(Some(&message));
+            }
+        }
+    }
 
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
+    // Runs at 60 frames per second, or as fast as the playback controls
+    // say, until the user asks for something else
+    pub fn interpret<R: Renderer, I: InputSource, A: AudioSink>(
+        &mut self,
+        renderer: &mut R,
+        input: &mut I,
+        audio: &mut A,
+    ) -> RunOutcome {
+        let mut status = self.playback.status();
+        renderer.set_status(status.as_deref());
+
+        loop {
+            let frame_start = Instant::now();
+
+            if self.playback.next_frame() {
+                if let Err(fault) = self.step(renderer, input, audio) {
+                    // Paused on the fault until the user resets or leaves
+                    renderer.set_error(Some(&fault.to_string()));
+                    if !self.playback.paused() {
+                        self.playback.handle(Action::Pause);
+                    }
+                }
+            } else {
+                // Paused, the window still takes hotkeys and gets redrawn
+                self.keypad = input.poll_idle();
+                audio.set_playing(false);
+                if self.screen.redraw {
+                    self.screen.redraw = false;
+                    renderer.present(&self.screen);
+                } else if renderer.needs_redraw() {
+                    renderer.redraw();
+                }
             }
 
-            self.instruction();
-            self.num_instructions += 1;
+            let actions = input.actions();
+            let open = actions.iter().find_map(|action| match action {
+                Action::Open(path) => Some(path.clone()),
+                _ => None,
+            });
+            let outcome = if actions.contains(&Action::Quit) {
+                Some(RunOutcome::Quit)
+            } else if let Some(path) = open {
+                Some(RunOutcome::Open(path))
+            } else if actions.contains(&Action::Menu) {
+                Some(RunOutcome::Menu)
+            } else {
+                None
+            };
+            if let Some(outcome) = outcome {
+                audio.set_playing(false);
+                renderer.set_status(None);
+                renderer.set_error(None);
+                return outcome;
+            }
+            for action in actions {
+                if action == Action::Reset {
+                    self.reload(renderer, true);
+                }
+                self.playback.handle(action);
+            }
+            if self.watcher.as_mut().is_some_and(FileWatcher::changed) {
+                self.reload(renderer, false);
+            }
+            if self.playback.status() != status {
+                status = self.playback.status();
+                renderer.set_status(status.as_deref());
+            }
 
-            // self.screen.canvas.present();
-            ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 720));
+            if let Some(remaining) = self
+                .playback
+                .frame_duration()
+                .checked_sub(frame_start.elapsed())
+            {
+                thread::sleep(remaining);
+            }
         }
     }
 }
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use crate::frontend::Action;
+
+use super::FRAME_DURATION;
+
+// Pause, frame advance, fast-forward and slow motion of `Chip::interpret`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playback {
+    // Speed while fast-forward is held and while slow motion is on
+    pub fast_forward_rate: f32,
+    pub slow_motion_rate: f32,
+    paused: bool,
+    fast_forward: bool,
+    slow_motion: bool,
+    // Frames to run before pausing again
+    advance: u32,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            fast_forward_rate: 4.0,
+            slow_motion_rate: 0.25,
+            paused: false,
+            fast_forward: false,
+            slow_motion: false,
+            advance: 0,
+        }
+    }
+}
+
+impl Playback {
+    pub fn handle(&mut self, action: Action) {
+        match action {
+            Action::Pause => {
+                self.paused = !self.paused;
+                self.advance = 0;
+            }
+            // Advancing a running game pauses it first
+            Action::FrameAdvance if self.paused => self.advance += 1,
+            Action::FrameAdvance => self.paused = true,
+            Action::FastForward(held) => self.fast_forward = held,
+            Action::SlowMotion => self.slow_motion = !self.slow_motion,
+            _ => {}
+        }
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    // Emulated time per real time, fast-forward wins over slow motion
+    pub fn rate(&self) -> f32 {
+        if self.fast_forward {
+            self.fast_forward_rate
+        } else if self.slow_motion {
+            self.slow_motion_rate
+        } else {
+            1.0
+        }
+    }
+
+    // Whether the next frame runs, using up a frame advance when paused
+    pub fn next_frame(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        if self.advance == 0 {
+            return false;
+        }
+        self.advance -= 1;
+        true
+    }
+
+    // Real time between two frames
+    pub fn frame_duration(&self) -> Duration {
+        if self.paused {
+            FRAME_DURATION
+        } else {
+            FRAME_DURATION.div_f32(self.rate())
+        }
+    }
+
+    // Shown to the user, None while running at normal speed
+    pub fn status(&self) -> Option<String> {
+        if self.paused {
+            Some("Paused".to_string())
+        } else if self.fast_forward {
+            Some(format!("Fast forward {}x", self.fast_forward_rate))
+        } else if self.slow_motion {
+            Some(format!("Slow motion {}x", self.slow_motion_rate))
+        } else {
+            None
+        }
+    }
+}
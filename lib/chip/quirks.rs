@@ -0,0 +1,97 @@
+// Behaviours that differ between CHIP-8 interpreters, named after Octo's
+// options. Everything off is how this emulator has always behaved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    // 8XY6/8XYE shift VX in place instead of copying VY first
+    pub shift: bool,
+    // FX55/FX65 leave I unchanged
+    pub load_store: bool,
+    // BNNN jumps to XNN + VX instead of NNN + V0
+    pub jump: bool,
+    // 8XY1/8XY2/8XY3 reset VF
+    pub logic: bool,
+    // Sprites are cut at the screen edges instead of wrapping around
+    pub clip: bool,
+    // DXYN waits for the next frame before carrying on
+    pub vblank: bool,
+}
+
+const NAMES: [&str; 6] = ["shift", "load-store", "jump", "logic", "clip", "vblank"];
+
+impl Quirks {
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        NAMES.into_iter()
+    }
+
+    fn flag(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "shift" => Some(&mut self.shift),
+            "load-store" | "loadstore" => Some(&mut self.load_store),
+            "jump" => Some(&mut self.jump),
+            "logic" => Some(&mut self.logic),
+            "clip" => Some(&mut self.clip),
+            "vblank" => Some(&mut self.vblank),
+            _ => None,
+        }
+    }
+
+    // A comma separated list of the quirks to turn on, "none" for none
+    pub fn parse(spec: &str) -> Result<Quirks, String> {
+        let mut quirks = Quirks::default();
+
+        for name in spec.split(',').map(|n| n.trim().to_ascii_lowercase()) {
+            if name.is_empty() || name == "none" {
+                continue;
+            }
+            *quirks.flag(&name).ok_or(format!(
+                "unknown quirk '{}', expected some of: {}",
+                name,
+                NAMES.join(", ")
+            ))? = true;
+        }
+
+        Ok(quirks)
+    }
+
+    // The inverse of `parse`
+    pub fn to_spec(&self) -> String {
+        let mut quirks = *self;
+        let names: Vec<&str> = NAMES
+            .into_iter()
+            .filter(|name| *quirks.flag(name).unwrap())
+            .collect();
+        if names.is_empty() {
+            "none".to_string()
+        } else {
+            names.join(",")
+        }
+    }
+}
+
+// How many instructions of `program` behave differently with each quirk.
+// Words are read from the start of the program, so data can be counted too.
+pub fn affected_instructions(program: &[u8]) -> Vec<(&'static str, usize)> {
+    let opcodes: Vec<u16> = program
+        .chunks_exact(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]))
+        .collect();
+    let count = |matches: fn(u16) -> bool| opcodes.iter().filter(|&&op| matches(op)).count();
+
+    vec![
+        (
+            "shift",
+            count(|op| op & 0xF00F == 0x8006 || op & 0xF00F == 0x800E),
+        ),
+        (
+            "load-store",
+            count(|op| op & 0xF0FF == 0xF055 || op & 0xF0FF == 0xF065),
+        ),
+        ("jump", count(|op| op & 0xF000 == 0xB000)),
+        (
+            "logic",
+            count(|op| op & 0xF00F == 0x8001 || op & 0xF00F == 0x8002 || op & 0xF00F == 0x8003),
+        ),
+        ("clip", count(|op| op & 0xF000 == 0xD000)),
+        ("vblank", count(|op| op & 0xF000 == 0xD000)),
+    ]
+}
@@ -0,0 +1,115 @@
+use std::{error::Error, fmt, fs, io, path::Path};
+
+use crate::assembler::{self, AsmError};
+use crate::cartridge::{self, CartError};
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    // The program does not fit between 0x200 and the end of memory
+    TooLarge { size: usize, max: usize },
+    Hex { line: usize, token: String },
+    Cartridge(String),
+    // An Octo source that does not assemble
+    Assembly(AsmError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "{}", err),
+            LoadError::TooLarge { size, max } => write!(
+                f,
+                "ROM too large for platform: {} bytes, at most {} fit in memory",
+                size, max
+            ),
+            LoadError::Hex { line, token } => {
+                write!(f, "line {}: '{}' is not a hex byte", line, token)
+            }
+            LoadError::Cartridge(err) => write!(f, "{}", err),
+            LoadError::Assembly(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoadError::Io(err) => Some(err),
+            LoadError::Assembly(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Assembler errors of a cartridge read like the ones of an Octo source
+impl From<CartError> for LoadError {
+    fn from(err: CartError) -> Self {
+        match err {
+            CartError::Io(err) => LoadError::Io(err),
+            CartError::Format(err) => LoadError::Cartridge(err),
+            CartError::Assembly(err) => LoadError::Assembly(err),
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(err: io::Error) -> Self {
+        LoadError::Io(err)
+    }
+}
+
+// Hex listings hold whitespace separated bytes, with or without a 0x prefix,
+// and may run several bytes together ("00E0 A22A"). Tokens ending in ':' are
+// addresses and are skipped, '#', ';' and "//" start comments.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, LoadError> {
+    let mut program = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let code = line
+            .split(['#', ';'])
+            .next()
+            .and_then(|code| code.split("//").next())
+            .unwrap_or("");
+
+        for token in code.split_whitespace() {
+            if token.ends_with(':') {
+                continue;
+            }
+
+            let digits = token
+                .strip_prefix("0x")
+                .or(token.strip_prefix("0X"))
+                .unwrap_or(token);
+            let error = || LoadError::Hex {
+                line: number + 1,
+                token: token.to_string(),
+            };
+
+            if digits.is_empty() || digits.len() % 2 != 0 || !digits.is_ascii() {
+                return Err(error());
+            }
+            for pair in digits.as_bytes().chunks(2) {
+                let pair = std::str::from_utf8(pair).map_err(|_| error())?;
+                program.push(u8::from_str_radix(pair, 16).map_err(|_| error())?);
+            }
+        }
+    }
+
+    Ok(program)
+}
+
+// The program in a ROM file: an Octo cartridge, Octo source, a hex listing
+// or raw bytes, told apart by the extension
+pub fn read(path: &Path) -> Result<Vec<u8>, LoadError> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("gif") => Ok(cartridge::load(path)?.program),
+        Some("8o") => assembler::assemble(&fs::read_to_string(path)?).map_err(LoadError::Assembly),
+        Some("hex") => parse_hex(&fs::read_to_string(path)?),
+        _ => Ok(fs::read(path)?),
+    }
+}
@@ -14,6 +14,10 @@ impl<T> Stack<T> {
         self.values.push(val)
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
     #[allow(dead_code)]
     pub fn pop(&mut self) -> T {
         self.values.pop().unwrap()
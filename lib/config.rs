@@ -0,0 +1,620 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use toml::{Table, Value};
+
+use crate::chip::Chip;
+use crate::chip::quirks::Quirks;
+use crate::database::{self, PLATFORMS};
+use crate::frontend::NUM_KEYS;
+use crate::frontend::keymap;
+use crate::frontend::macros::Macro;
+use crate::frontend::sdl::SdlOptions;
+use crate::graphics::Protocol;
+use crate::palette::Palette;
+use crate::render::crt::{self, CrtSettings};
+use crate::render::persistence::PersistenceMode;
+use crate::render::scaler::Scaler;
+
+// $XDG_CONFIG_HOME/rust_chip8, or ~/.config/rust_chip8
+pub fn config_dir() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config.join("rust_chip8"))
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("config.toml"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub file: String,
+    // Dotted path of the offending setting, empty for syntax errors
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}: {}: {}", self.file, self.key, self.message)
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+pub const KEYS: [&str; 22] = [
+    "platform",
+    "quirks",
+    "ipf",
+    "seed",
+    "fast-forward",
+    "slow-motion",
+    "palette",
+    "scale",
+    "fullscreen",
+    "integer-scaling",
+    "persistence",
+    "scaler",
+    "crt",
+    "osd",
+    "screenshot-osd",
+    "keypad",
+    "autofire-rate",
+    "volume",
+    "tone",
+    "mute",
+    "terminal",
+    "terminal-scale",
+];
+
+// One source of settings: the config file, one of its ROM sections or the
+// command line. Unset settings fall through to the layer below.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Layer {
+    // Where the settings come from, shown by `config dump`
+    pub source: String,
+    pub platform: Option<String>,
+    pub quirks: Option<Quirks>,
+    pub ipf: Option<u32>,
+    pub seed: Option<u64>,
+    pub fast_forward: Option<f32>,
+    pub slow_motion: Option<f32>,
+    pub palette: Option<Palette>,
+    pub scale: Option<usize>,
+    pub fullscreen: Option<bool>,
+    pub integer_scaling: Option<bool>,
+    // Some(None) turns persistence off again
+    pub persistence: Option<Option<PersistenceMode>>,
+    pub scaler: Option<Scaler>,
+    pub crt: Option<bool>,
+    // Strengths from the [crt] table, in the order of `crt::EFFECTS`
+    pub crt_effects: [Option<f32>; crt::EFFECTS.len()],
+    pub osd: Option<bool>,
+    pub screenshot_osd: Option<bool>,
+    pub keypad: Option<bool>,
+    pub autofire_rate: Option<u32>,
+    pub volume: Option<f32>,
+    pub tone: Option<f32>,
+    pub mute: Option<bool>,
+    // Some(None) goes back to the window
+    pub terminal: Option<Option<Protocol>>,
+    pub terminal_scale: Option<usize>,
+    pub keys: [Option<Vec<String>>; NUM_KEYS],
+    // Host keys toggling autofire of each CHIP-8 key
+    pub autofire: [Option<Vec<String>>; NUM_KEYS],
+    // Macros by the host key starting them
+    pub macros: BTreeMap<String, Macro>,
+}
+
+fn expect_str<'a>(value: &'a Value, what: &str) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or(format!("expected {}, found {}", what, value.type_str()))
+}
+
+fn expect_bool(value: &Value) -> Result<bool, String> {
+    value.as_bool().ok_or(format!(
+        "expected true or false, found {}",
+        value.type_str()
+    ))
+}
+
+fn expect_integer(value: &Value, min: i64, max: i64) -> Result<i64, String> {
+    match value.as_integer() {
+        Some(n) if (min..=max).contains(&n) => Ok(n),
+        Some(n) => Err(format!("{} is out of range {}..{}", n, min, max)),
+        None => Err(format!("expected an integer, found {}", value.type_str())),
+    }
+}
+
+fn expect_float(value: &Value, min: f64, max: f64) -> Result<f64, String> {
+    let n = match value {
+        Value::Float(n) => *n,
+        Value::Integer(n) => *n as f64,
+        _ => return Err(format!("expected a number, found {}", value.type_str())),
+    };
+    if !(min..=max).contains(&n) {
+        return Err(format!("{} is out of range {}..{}", n, min, max));
+    }
+    Ok(n)
+}
+
+// A string or an array of strings
+fn expect_strings(value: &Value, what: &str) -> Result<Vec<String>, String> {
+    match value {
+        Value::String(s) => Ok(vec![s.clone()]),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| expect_str(item, what).map(str::to_string))
+            .collect(),
+        _ => Err(format!(
+            "expected {} or a list of them, found {}",
+            what,
+            value.type_str()
+        )),
+    }
+}
+
+fn hotkey_error(hotkey: &str) -> String {
+    format!("{} is a hotkey of the emulator and cannot be bound", hotkey)
+}
+
+// A [keys] or [autofire] table, CHIP-8 keys 0 to F bound to host key names
+fn set_bindings(
+    bindings: &mut [Option<Vec<String>>; NUM_KEYS],
+    table: &Table,
+    prefix: &str,
+) -> Result<(), (String, String)> {
+    for (name, value) in table {
+        let key = format!("{}.{}", prefix, name);
+        let index = match u8::from_str_radix(name, 16) {
+            Ok(index) if name.len() == 1 => index,
+            _ => return Err((key, "expected a CHIP-8 key from 0 to F".to_string())),
+        };
+        let names = expect_strings(value, "a key name").map_err(|err| (key.clone(), err))?;
+        if let Some(hotkey) = names.iter().find_map(|name| keymap::hotkey(name)) {
+            return Err((key, hotkey_error(hotkey)));
+        }
+        bindings[index as usize] = Some(names);
+    }
+    Ok(())
+}
+
+impl Layer {
+    pub fn new(source: &str) -> Layer {
+        Layer {
+            source: source.to_string(),
+            ..Layer::default()
+        }
+    }
+
+    // Sets one setting from its config file or command line value
+    pub fn set(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match key {
+            "platform" => {
+                let name = expect_str(value, "a platform name")?;
+                database::platform_quirks(name).ok_or(format!(
+                    "unknown platform '{}', expected one of: {}",
+                    name,
+                    PLATFORMS.join(", ")
+                ))?;
+                self.platform = Some(name.to_string());
+            }
+            "quirks" => {
+                let names = expect_strings(value, "a quirk name")?;
+                self.quirks = Some(Quirks::parse(&names.join(","))?);
+            }
+            "ipf" | "tickrate" => self.ipf = Some(expect_integer(value, 1, 100_000)? as u32),
+            "seed" => self.seed = Some(expect_integer(value, 0, i64::MAX)? as u64),
+            "fast-forward" => self.fast_forward = Some(expect_float(value, 1.0, 64.0)? as f32),
+            "slow-motion" => self.slow_motion = Some(expect_float(value, 0.01, 1.0)? as f32),
+            "palette" => {
+                let colors = expect_strings(value, "a palette name or colour")?;
+                self.palette = Some(match colors.as_slice() {
+                    [spec] => Palette::parse(spec)?,
+                    _ => Palette::from_hex_list(
+                        &colors.iter().map(String::as_str).collect::<Vec<_>>(),
+                    )?,
+                });
+            }
+            "scale" => self.scale = Some(expect_integer(value, 1, 100)? as usize),
+            "fullscreen" => self.fullscreen = Some(expect_bool(value)?),
+            "integer-scaling" => self.integer_scaling = Some(expect_bool(value)?),
+            "persistence" => {
+                let spec = expect_str(value, "fade:N, blend:N or none")?;
+                self.persistence = Some(match spec {
+                    "none" | "off" => None,
+                    _ => Some(PersistenceMode::parse(spec)?),
+                });
+            }
+            "scaler" => self.scaler = Some(Scaler::parse(expect_str(value, "a scaler name")?)?),
+            "crt" => self.crt = Some(expect_bool(value)?),
+            "osd" => self.osd = Some(expect_bool(value)?),
+            "screenshot-osd" => self.screenshot_osd = Some(expect_bool(value)?),
+            "keypad" => self.keypad = Some(expect_bool(value)?),
+            "autofire-rate" => self.autofire_rate = Some(expect_integer(value, 1, 60)? as u32),
+            "volume" => self.volume = Some(expect_float(value, 0.0, 1.0)? as f32),
+            "tone" => self.tone = Some(expect_float(value, 20.0, 20_000.0)? as f32),
+            "mute" => self.mute = Some(expect_bool(value)?),
+            "terminal" => {
+                let name = expect_str(value, "sixel, kitty or none")?;
+                self.terminal = Some(match name {
+                    "none" | "off" => None,
+                    _ => Some(Protocol::parse(name)?),
+                });
+            }
+            "terminal-scale" => self.terminal_scale = Some(expect_integer(value, 1, 16)? as usize),
+            _ => {
+                return Err(format!(
+                    "unknown setting, expected one of: {}, keys, autofire, macros",
+                    KEYS.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // A [crt] table of effect strengths, which turns the effects on
+    fn set_crt(&mut self, table: &Table, prefix: &str) -> Result<(), (String, String)> {
+        for (name, value) in table {
+            let key = format!("{}.{}", prefix, name);
+            let Some(index) = crt::EFFECTS.iter().position(|(effect, _)| effect == name) else {
+                let names: Vec<&str> = crt::EFFECTS.iter().map(|(effect, _)| *effect).collect();
+                return Err((
+                    key,
+                    format!("unknown effect, expected one of: {}", names.join(", ")),
+                ));
+            };
+            let max = crt::EFFECTS[index].1 as f64;
+            let strength = expect_float(value, 0.0, max).map_err(|err| (key, err))?;
+            self.crt_effects[index] = Some(strength as f32);
+        }
+        self.crt = Some(true);
+        Ok(())
+    }
+
+    // The CRT effects with the strengths this layer sets
+    pub fn crt_settings(&self) -> CrtSettings {
+        let mut settings = CrtSettings::default();
+        for ((name, _), strength) in crt::EFFECTS.iter().zip(self.crt_effects) {
+            if let (Some(effect), Some(strength)) = (settings.effect_mut(name), strength) {
+                *effect = strength;
+            }
+        }
+        settings
+    }
+
+    // A [macros] table, host key names bound to lists of steps
+    fn set_macros(&mut self, table: &Table, prefix: &str) -> Result<(), (String, String)> {
+        for (name, value) in table {
+            let key = format!("{}.{:?}", prefix, name);
+            if let Some(hotkey) = keymap::hotkey(name) {
+                return Err((key, hotkey_error(hotkey)));
+            }
+            let steps = expect_strings(value, "a macro step").map_err(|err| (key.clone(), err))?;
+            let steps = Macro::parse(&steps).map_err(|err| (key, err))?;
+            self.macros.insert(name.clone(), steps);
+        }
+        Ok(())
+    }
+
+    fn parse(table: &Table, source: &str, prefix: &str) -> Result<Layer, (String, String)> {
+        let mut layer = Layer::new(source);
+        for (name, value) in table {
+            let key = format!("{}{}", prefix, name);
+            match (name.as_str(), value) {
+                ("keys", Value::Table(keys)) => set_bindings(&mut layer.keys, keys, &key)?,
+                ("autofire", Value::Table(keys)) => set_bindings(&mut layer.autofire, keys, &key)?,
+                ("macros", Value::Table(macros)) => layer.set_macros(macros, &key)?,
+                ("crt", Value::Table(effects)) => layer.set_crt(effects, &key)?,
+                ("keys" | "autofire" | "macros", _) => {
+                    return Err((key, "expected a table".to_string()));
+                }
+                _ => layer.set(name, value).map_err(|err| (key, err))?,
+            }
+        }
+        Ok(layer)
+    }
+
+    // Settings of `other` replace the ones of this layer
+    pub fn overlay(&mut self, other: &Layer) {
+        // A platform and quirks both pick the quirks, the higher layer wins
+        if other.platform.is_some() && other.quirks.is_none() {
+            self.quirks = None;
+        }
+        if other.quirks.is_some() && other.platform.is_none() {
+            self.platform = None;
+        }
+        macro_rules! overlay {
+            ($($field:ident),*) => {
+                $(if other.$field.is_some() {
+                    self.$field = other.$field.clone();
+                })*
+            };
+        }
+        overlay!(
+            platform,
+            quirks,
+            ipf,
+            seed,
+            fast_forward,
+            slow_motion,
+            palette,
+            scale,
+            fullscreen,
+            integer_scaling,
+            persistence,
+            scaler,
+            crt,
+            osd,
+            screenshot_osd,
+            keypad,
+            autofire_rate,
+            volume,
+            tone,
+            mute,
+            terminal,
+            terminal_scale
+        );
+        for (key, names) in self.keys.iter_mut().zip(&other.keys) {
+            if names.is_some() {
+                *key = names.clone();
+            }
+        }
+        for (key, names) in self.autofire.iter_mut().zip(&other.autofire) {
+            if names.is_some() {
+                *key = names.clone();
+            }
+        }
+        for (effect, strength) in self.crt_effects.iter_mut().zip(other.crt_effects) {
+            if strength.is_some() {
+                *effect = strength;
+            }
+        }
+        for (name, steps) in &other.macros {
+            self.macros.insert(name.clone(), steps.clone());
+        }
+    }
+
+    pub fn merged(layers: &[Layer]) -> Layer {
+        let mut merged = Layer::new("merged");
+        for layer in layers {
+            merged.overlay(layer);
+        }
+        merged
+    }
+
+    // Whether this layer sets `key`, one of `KEYS`, "keys.N", "autofire.N",
+    // "macros.NAME" or "crt.EFFECT"
+    pub fn sets(&self, key: &str) -> bool {
+        match key {
+            "platform" => self.platform.is_some(),
+            "quirks" => self.quirks.is_some(),
+            "ipf" => self.ipf.is_some(),
+            "seed" => self.seed.is_some(),
+            "fast-forward" => self.fast_forward.is_some(),
+            "slow-motion" => self.slow_motion.is_some(),
+            "palette" => self.palette.is_some(),
+            "scale" => self.scale.is_some(),
+            "fullscreen" => self.fullscreen.is_some(),
+            "integer-scaling" => self.integer_scaling.is_some(),
+            "persistence" => self.persistence.is_some(),
+            "scaler" => self.scaler.is_some(),
+            "crt" => self.crt.is_some(),
+            "osd" => self.osd.is_some(),
+            "screenshot-osd" => self.screenshot_osd.is_some(),
+            "keypad" => self.keypad.is_some(),
+            "autofire-rate" => self.autofire_rate.is_some(),
+            "volume" => self.volume.is_some(),
+            "tone" => self.tone.is_some(),
+            "mute" => self.mute.is_some(),
+            "terminal" => self.terminal.is_some(),
+            "terminal-scale" => self.terminal_scale.is_some(),
+            _ => {
+                let key_set = |keys: &[Option<Vec<String>>], n: &str| {
+                    u8::from_str_radix(n, 16)
+                        .ok()
+                        .is_some_and(|n| keys.get(n as usize).is_some_and(Option::is_some))
+                };
+                match key.split_once('.') {
+                    Some(("keys", n)) => key_set(&self.keys, n),
+                    Some(("autofire", n)) => key_set(&self.autofire, n),
+                    Some(("macros", name)) => self.macros.contains_key(name),
+                    Some(("crt", name)) => crt::EFFECTS
+                        .iter()
+                        .position(|(effect, _)| *effect == name)
+                        .is_some_and(|index| self.crt_effects[index].is_some()),
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    // The emulation settings, over whatever the ROM asked for. Quirks given
+    // by name win over a platform set in the same layer.
+    pub fn apply(&self, chip: &mut Chip) {
+        let platform = self.platform.as_deref().and_then(database::platform_quirks);
+        if let Some(quirks) = self.quirks.or(platform) {
+            chip.set_quirks(quirks);
+        }
+        if let Some(ipf) = self.ipf {
+            chip.set_tickrate(ipf);
+        }
+        if let Some(seed) = self.seed {
+            chip.set_seed(seed);
+        }
+        if let Some(rate) = self.fast_forward {
+            chip.playback_mut().fast_forward_rate = rate;
+        }
+        if let Some(rate) = self.slow_motion {
+            chip.playback_mut().slow_motion_rate = rate;
+        }
+    }
+
+    // The window, audio, key and macro settings
+    pub fn apply_window(&self, options: &mut SdlOptions) {
+        if let Some(palette) = &self.palette {
+            options.palette = palette.clone();
+        }
+        if let Some(scale) = self.scale {
+            options.scale = scale;
+        }
+        if let Some(fullscreen) = self.fullscreen {
+            options.fullscreen = fullscreen;
+        }
+        if let Some(integer_scaling) = self.integer_scaling {
+            options.integer_scaling = integer_scaling;
+        }
+        if let Some(persistence) = self.persistence {
+            options.persistence = persistence;
+        }
+        if let Some(scaler) = self.scaler {
+            options.scaler = scaler;
+        }
+        if let Some(crt) = self.crt {
+            options.crt = crt.then(|| self.crt_settings());
+        }
+        if let Some(osd) = self.osd {
+            options.osd = osd;
+        }
+        if let Some(screenshot_osd) = self.screenshot_osd {
+            options.screenshot_osd = screenshot_osd;
+        }
+        if let Some(keypad) = self.keypad {
+            options.keypad = keypad;
+        }
+        if let Some(volume) = self.volume {
+            options.volume = volume;
+        }
+        if let Some(tone) = self.tone {
+            options.tone_hz = tone;
+        }
+        if self.mute == Some(true) {
+            options.volume = 0.0;
+        }
+        if let Some(terminal) = self.terminal {
+            options.terminal = terminal;
+        }
+        if let Some(scale) = self.terminal_scale {
+            options.terminal_scale = scale;
+        }
+        for (key, names) in self.keys.iter().enumerate() {
+            if let Some(names) = names {
+                options.keymap.bind(key as u8, names.clone());
+            }
+        }
+        if let Some(rate) = self.autofire_rate {
+            options.macros.autofire_rate = rate;
+        }
+        for (key, names) in self.autofire.iter().enumerate() {
+            if let Some(names) = names {
+                options.macros.bind_autofire(key as u8, names.clone());
+            }
+        }
+        for (name, steps) in &self.macros {
+            options.macros.bind_macro(name, steps.clone());
+        }
+    }
+}
+
+// A config file: settings for every ROM, then [rom."<name or SHA-1>"]
+// sections for single ROMs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub base: Layer,
+    pub roms: Vec<(String, Layer)>,
+}
+
+impl Config {
+    pub fn parse(text: &str, file: &str) -> Result<Config, ConfigError> {
+        let error = |key: String, message: String| ConfigError {
+            file: file.to_string(),
+            key,
+            message,
+        };
+
+        let mut table: Table = text.parse().map_err(|err: toml::de::Error| {
+            let line = err
+                .span()
+                .map_or(1, |span| text[..span.start].matches('\n').count() + 1);
+            let message = err.message().replace('\n', ", ");
+            error(String::new(), format!("line {}: {}", line, message))
+        })?;
+
+        let mut roms = Vec::new();
+        match table.remove("rom") {
+            Some(Value::Table(sections)) => {
+                for (name, section) in sections {
+                    let prefix = format!("rom.\"{}\".", name);
+                    let Value::Table(section) = section else {
+                        return Err(error(
+                            prefix.trim_end_matches('.').to_string(),
+                            "expected a table".to_string(),
+                        ));
+                    };
+                    let source = format!("{} [rom.\"{}\"]", file, name);
+                    let layer = Layer::parse(&section, &source, &prefix)
+                        .map_err(|(key, message)| error(key, message))?;
+                    roms.push((name, layer));
+                }
+            }
+            Some(_) => return Err(error("rom".to_string(), "expected a table".to_string())),
+            None => {}
+        }
+
+        let base = Layer::parse(&table, file, "").map_err(|(key, message)| error(key, message))?;
+        Ok(Config { base, roms })
+    }
+
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let text = fs::read_to_string(path).map_err(|err| ConfigError {
+            file: path.display().to_string(),
+            key: String::new(),
+            message: err.to_string(),
+        })?;
+        Config::parse(&text, &path.display().to_string())
+    }
+
+    // The user's config file, a missing one is the same as an empty one
+    pub fn load_default() -> Result<Config, ConfigError> {
+        match default_path() {
+            Some(path) if path.exists() => Config::load(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    // The layers that apply to a ROM, least specific first: the whole file,
+    // sections matching its file name, then sections matching its SHA-1
+    pub fn layers(&self, rom: Option<&Path>, program: Option<&[u8]>) -> Vec<Layer> {
+        let file_name = rom.and_then(Path::file_name).map(|n| n.to_string_lossy());
+        let stem = rom.and_then(Path::file_stem).map(|n| n.to_string_lossy());
+        let sha1 = program.map(database::sha1);
+
+        let by_name = self.roms.iter().filter(|(name, _)| {
+            [&file_name, &stem]
+                .into_iter()
+                .flatten()
+                .any(|n| n.eq_ignore_ascii_case(name))
+        });
+        let by_hash = self.roms.iter().filter(|(name, _)| {
+            sha1.as_ref()
+                .is_some_and(|sha1| sha1.eq_ignore_ascii_case(name))
+        });
+
+        std::iter::once(&self.base)
+            .chain(by_name.chain(by_hash).map(|(_, layer)| layer))
+            .cloned()
+            .collect()
+    }
+}
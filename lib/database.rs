@@ -0,0 +1,211 @@
+use std::{collections::HashMap, fs, path::Path, sync::OnceLock};
+
+use serde_json::{Map, Value};
+
+use crate::chip::quirks::Quirks;
+use crate::palette::Palette;
+
+// ROM metadata in the format of the community chip-8-database programs.json:
+// a list of programs, each with the ROMs it was released as keyed by their
+// SHA-1. Only the first platform of a ROM is used to pick its quirks.
+const BUNDLED: &str = include_str!("../data/programs.json");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub platform: Option<String>,
+    // None when the platform is unknown, the quirks are left alone then
+    pub quirks: Option<Quirks>,
+    pub tickrate: Option<u32>,
+    pub palette: Option<Palette>,
+    // What the game uses each key for, such as ("left", 4)
+    pub keys: Vec<(String, u8)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Database {
+    roms: HashMap<String, RomInfo>,
+}
+
+pub const PLATFORMS: [&str; 7] = [
+    "originalChip8",
+    "hybridVIP",
+    "modernChip8",
+    "chip48",
+    "superchip1",
+    "superchip",
+    "xochip",
+];
+
+// Quirks of the platforms this emulator can behave like
+pub fn platform_quirks(platform: &str) -> Option<Quirks> {
+    match platform {
+        "originalChip8" | "hybridVIP" => Some(Quirks {
+            logic: true,
+            clip: true,
+            vblank: true,
+            ..Quirks::default()
+        }),
+        "modernChip8" => Some(Quirks {
+            clip: true,
+            ..Quirks::default()
+        }),
+        "chip48" | "superchip1" | "superchip" => Some(Quirks {
+            shift: true,
+            load_store: true,
+            jump: true,
+            clip: true,
+            ..Quirks::default()
+        }),
+        "xochip" => Some(Quirks::default()),
+        _ => None,
+    }
+}
+
+fn apply_overrides(quirks: &mut Quirks, overrides: &Map<String, Value>) {
+    for (name, value) in overrides {
+        let Some(on) = value.as_bool() else {
+            continue;
+        };
+        match name.as_str() {
+            "shift" => quirks.shift = on,
+            "memoryLeaveIUnchanged" => quirks.load_store = on,
+            "jump" => quirks.jump = on,
+            "logic" => quirks.logic = on,
+            "wrap" => quirks.clip = !on,
+            "vblank" => quirks.vblank = on,
+            _ => {}
+        }
+    }
+}
+
+fn rom_info(program: &Map<String, Value>, rom: &Map<String, Value>) -> Result<RomInfo, String> {
+    let title = program
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or("Unknown")
+        .to_string();
+    let authors = program
+        .get("authors")
+        .and_then(Value::as_array)
+        .map(|authors| {
+            authors
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let platform = rom
+        .get("platforms")
+        .and_then(Value::as_array)
+        .and_then(|platforms| platforms.first())
+        .and_then(Value::as_str)
+        .map(str::to_string);
+
+    let mut quirks = platform.as_deref().and_then(platform_quirks);
+    if let (Some(quirks), Some(platform)) = (&mut quirks, &platform)
+        && let Some(Value::Object(overrides)) = rom
+            .get("quirkyPlatforms")
+            .and_then(|quirky| quirky.get(platform))
+    {
+        apply_overrides(quirks, overrides);
+    }
+
+    let tickrate = rom
+        .get("tickrate")
+        .and_then(Value::as_u64)
+        .map(|t| t as u32);
+
+    let pixels: Vec<&str> = rom
+        .get("colors")
+        .and_then(|colors| colors.get("pixels"))
+        .and_then(Value::as_array)
+        .map(|pixels| pixels.iter().filter_map(Value::as_str).take(4).collect())
+        .unwrap_or_default();
+    let palette = if pixels.len() >= 2 {
+        let mut palette =
+            Palette::from_hex_list(&pixels).map_err(|err| format!("{}: {}", title, err))?;
+        palette.name = title.clone();
+        Some(palette)
+    } else {
+        None
+    };
+
+    let keys = match rom.get("keys") {
+        Some(Value::Object(keys)) => keys
+            .iter()
+            .filter_map(|(action, key)| Some((action.clone(), key.as_u64()? as u8)))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(RomInfo {
+        title,
+        authors,
+        platform,
+        quirks,
+        tickrate,
+        palette,
+        keys,
+    })
+}
+
+impl Database {
+    pub fn parse(json: &str) -> Result<Database, String> {
+        let programs: Value =
+            serde_json::from_str(json).map_err(|err| format!("broken ROM database: {}", err))?;
+        let programs = programs
+            .as_array()
+            .ok_or("the ROM database is not a list of programs")?;
+
+        let mut roms = HashMap::new();
+        for program in programs.iter().filter_map(Value::as_object) {
+            let Some(Value::Object(entries)) = program.get("roms") else {
+                continue;
+            };
+            for (hash, rom) in entries {
+                if let Some(rom) = rom.as_object() {
+                    roms.insert(hash.to_ascii_lowercase(), rom_info(program, rom)?);
+                }
+            }
+        }
+
+        Ok(Database { roms })
+    }
+
+    pub fn load(path: &Path) -> Result<Database, String> {
+        let json = fs::read_to_string(path)
+            .map_err(|err| format!("Cannot read {}: {}", path.display(), err))?;
+        Database::parse(&json)
+    }
+
+    // The database shipped with the emulator, covering the ROMs in roms/
+    pub fn bundled() -> &'static Database {
+        static DATABASE: OnceLock<Database> = OnceLock::new();
+        DATABASE
+            .get_or_init(|| Database::parse(BUNDLED).expect("the bundled ROM database is valid"))
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    pub fn get(&self, sha1: &str) -> Option<&RomInfo> {
+        self.roms.get(&sha1.to_ascii_lowercase())
+    }
+
+    pub fn lookup(&self, program: &[u8]) -> Option<&RomInfo> {
+        self.get(&sha1(program))
+    }
+}
+
+pub fn sha1(program: &[u8]) -> String {
+    sha1_smol::Sha1::from(program).digest().to_string()
+}
@@ -0,0 +1,125 @@
+use std::collections::BTreeSet;
+
+const PROGRAM_START: u16 = 0x200;
+
+// Octo statement for an instruction, None for words that are not one
+pub fn mnemonic(opcode: u16) -> Option<String> {
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+    let n = opcode & 0xF;
+    let nn = opcode & 0xFF;
+    let nnn = opcode & 0xFFF;
+
+    let statement = match opcode >> 12 {
+        0x0 if opcode == 0x00E0 => "clear".to_string(),
+        0x0 if opcode == 0x00EE => "return".to_string(),
+        0x1 => format!("jump 0x{:03X}", nnn),
+        0x2 => format!(":call 0x{:03X}", nnn),
+        0x3 => format!("if v{:X} != 0x{:02X} then", x, nn),
+        0x4 => format!("if v{:X} == 0x{:02X} then", x, nn),
+        0x5 if n == 0 => format!("if v{:X} != v{:X} then", x, y),
+        0x6 => format!("v{:X} := 0x{:02X}", x, nn),
+        0x7 => format!("v{:X} += 0x{:02X}", x, nn),
+        0x8 => {
+            let operator = match n {
+                0x0 => ":=",
+                0x1 => "|=",
+                0x2 => "&=",
+                0x3 => "^=",
+                0x4 => "+=",
+                0x5 => "-=",
+                0x6 => ">>=",
+                0x7 => "=-",
+                0xE => "<<=",
+                _ => return None,
+            };
+            format!("v{:X} {} v{:X}", x, operator, y)
+        }
+        0x9 if n == 0 => format!("if v{:X} == v{:X} then", x, y),
+        0xA => format!("i := 0x{:03X}", nnn),
+        0xB => format!("jump0 0x{:03X}", nnn),
+        0xC => format!("v{:X} := random 0x{:02X}", x, nn),
+        0xD => format!("sprite v{:X} v{:X} {}", x, y, n),
+        0xE if nn == 0x9E => format!("if v{:X} -key then", x),
+        0xE if nn == 0xA1 => format!("if v{:X} key then", x),
+        0xF => match nn {
+            0x07 => format!("v{:X} := delay", x),
+            0x0A => format!("v{:X} := key", x),
+            0x15 => format!("delay := v{:X}", x),
+            0x18 => format!("buzzer := v{:X}", x),
+            0x1E => format!("i += v{:X}", x),
+            0x29 => format!("i := hex v{:X}", x),
+            0x33 => format!("bcd v{:X}", x),
+            0x55 => format!("save v{:X}", x),
+            0x65 => format!("load v{:X}", x),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(statement)
+}
+
+// Addresses the program jumps to, calls or points I at
+fn targets(program: &[u8]) -> BTreeSet<u16> {
+    program
+        .chunks_exact(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]))
+        .filter(|opcode| matches!(opcode >> 12, 0x1 | 0x2 | 0xA | 0xB))
+        .map(|opcode| opcode & 0xFFF)
+        .collect()
+}
+
+// Octo source for `program`, one instruction per line. Words are decoded
+// from the start of the program, data shows up as instructions or bytes,
+// but the source always assembles back to the same program.
+pub fn disassemble(program: &[u8]) -> String {
+    let end = PROGRAM_START + program.len() as u16;
+    let labels: BTreeSet<u16> = targets(program)
+        .into_iter()
+        .filter(|&target| (PROGRAM_START..end).contains(&target) && target % 2 == 0)
+        .collect();
+    let label = |address: u16| format!("label_{:03X}", address);
+
+    let mut source = String::new();
+    for (index, word) in program.chunks(2).enumerate() {
+        let address = PROGRAM_START + 2 * index as u16;
+        if labels.contains(&address) {
+            source.push_str(&format!(": {}\n", label(address)));
+        }
+
+        let statement = match word {
+            [high, low] => {
+                let opcode = u16::from_be_bytes([*high, *low]);
+                mnemonic(opcode).map(|statement| {
+                    // Point at labels rather than addresses where there is one
+                    let target = opcode & 0xFFF;
+                    match statement.rsplit_once(' ') {
+                        Some((head, _))
+                            if matches!(opcode >> 12, 0x1 | 0x2 | 0xA | 0xB)
+                                && labels.contains(&target) =>
+                        {
+                            format!("{} {}", head, label(target))
+                        }
+                        _ => statement,
+                    }
+                })
+            }
+            _ => None,
+        };
+        let bytes: Vec<String> = word.iter().map(|b| format!("0x{:02X}", b)).collect();
+        let raw: String = word.iter().map(|b| format!("{:02X}", b)).collect();
+
+        match statement {
+            Some(statement) => {
+                source.push_str(&format!("\t{:<24}# {:03X}: {}\n", statement, address, raw))
+            }
+            None => source.push_str(&format!(
+                "\t{:<24}# {:03X}: {}\n",
+                bytes.join(" "),
+                address,
+                raw
+            )),
+        }
+    }
+    source
+}
@@ -1,23 +1,18 @@
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
-use sdl2::{EventPump, Sdl, VideoSubsystem};
+use crate::graphics::TerminalGraphics;
 
 pub const WIDTH: usize = 64;
 pub const HEIGHT: usize = 32;
 
-pub const PIX_SIZE: usize = 20;
+// The framebuffer handed to a frontend's renderer
+pub type Framebuffer = Display;
 
+#[derive(Clone)]
 pub struct Display {
-    // data: [[bool; HEIGHT]; WIDTH],
-    pub data: [bool; WIDTH * HEIGHT],
-    sdl_context: Sdl,
-    video_subsystem: VideoSubsystem,
-    // window: Window,
-    pub canvas: Canvas<Window>,
-    pub event_pump: EventPump,
+    // Row by row, `width` pixels each
+    pub data: Vec<bool>,
     pub redraw: bool,
+    width: usize,
+    height: usize,
 }
 
 impl Default for Display {
@@ -28,71 +23,44 @@ impl Default for Display {
 
 impl Display {
     pub fn new() -> Self {
-        let sdl_context = sdl2::init().unwrap();
-        let video_subsystem = sdl_context
-            .video()
-            .expect("Cannot initialize video subsystem!");
-
-        let window = video_subsystem
-            .window(
-                "Chip 8 Emulator",
-                (WIDTH * PIX_SIZE) as u32,
-                (HEIGHT * PIX_SIZE) as u32,
-            )
-            .position_centered()
-            .build()
-            .unwrap();
-
-        let mut canvas = window.into_canvas().build().unwrap();
-
-        canvas.set_draw_color(Color::BLACK);
-        canvas.clear();
-        canvas.present();
-
-        let event_pump = sdl_context.event_pump().unwrap();
+        Display::with_size(WIDTH, HEIGHT)
+    }
 
+    // A screen of another resolution, such as the 128x64 of SUPER-CHIP
+    pub fn with_size(width: usize, height: usize) -> Self {
         Display {
-            data: [false; WIDTH * HEIGHT],
-            sdl_context: sdl_context,
-            video_subsystem: video_subsystem,
-            canvas: canvas,
-            event_pump: event_pump,
-            redraw: true
+            data: vec![false; width * height],
+            redraw: true,
+            width,
+            height,
         }
     }
 
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     pub fn clear_screen(&mut self) {
         self.data.fill(false);
+        self.redraw = true;
     }
 
     pub fn set_pixel(&mut self, row: usize, col: usize, val: bool) {
-        *self.data.get_mut(row * WIDTH + col).unwrap() = val;
+        *self.data.get_mut(row * self.width + col).unwrap() = val;
     }
 
     pub fn get_pixel(&self, row: usize, col: usize) -> bool {
-        *self.data.get(row * WIDTH + col).unwrap()
+        *self.data.get(row * self.width + col).unwrap()
     }
 
-    pub fn create_white_rects(&self) -> Vec<Rect> {
-        let mut rects: Vec<Rect> = Vec::new();
-    
-        for i in 0..WIDTH {
-            for j in 0..HEIGHT {
-                if self.get_pixel(j, i) == true {
-                // if self.data[j * WIDTH + i] == true {
-                    // TODO: Attenzione (j * PIX_SIZE - 1) potrebbe essere
-                    rects.push(Rect::new((i * PIX_SIZE) as i32, (j * PIX_SIZE) as i32, PIX_SIZE as u32, PIX_SIZE as u32));
-                }
-            }
-        }
-    
-        rects
-    }
-    
     pub fn display_terminal(&self) {
-        for col in 0..HEIGHT {
-            for row in 0..WIDTH {
-                if !self.get_pixel(row, col) {
+        for col in 0..self.height {
+            for row in 0..self.width {
+                if !self.get_pixel(col, row) {
                     print!(" ");
                 } else {
                     print!("o");
@@ -101,4 +69,23 @@ impl Display {
             println!();
         }
     }
+
+    // One line per row, '#' for lit pixels and '.' for the others
+    pub fn to_text(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.height);
+        for row in self.data.chunks(self.width) {
+            text.extend(row.iter().map(|&lit| if lit { '#' } else { '.' }));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn display_graphics(&self, graphics: &TerminalGraphics) {
+        // Home the cursor so every frame overwrites the previous one
+        print!(
+            "\x1b[H{}",
+            graphics.encode(&self.data, self.width, self.height)
+        );
+        println!();
+    }
 }
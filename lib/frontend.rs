@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use crate::display::Framebuffer;
+use crate::image::Image;
+
+pub mod gamepad;
+pub mod headless;
+pub mod image_sequence;
+pub mod keymap;
+pub mod macros;
+pub mod movie;
+pub mod recording;
+pub mod sdl;
+pub mod terminal;
+pub mod video;
+
+pub const NUM_KEYS: usize = 16;
+
+// The buzzer is a square wave at this pitch and amplitude
+pub const TONE_HZ: f32 = 440.0;
+pub const VOLUME: f32 = 0.15;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeypadState {
+    keys: [bool; NUM_KEYS],
+}
+
+impl KeypadState {
+    pub fn press(&mut self, key: u8) {
+        self.keys[(key & 0xF) as usize] = true;
+    }
+
+    pub fn release(&mut self, key: u8) {
+        self.keys[(key & 0xF) as usize] = false;
+    }
+
+    pub fn is_pressed(&self, key: u8) -> bool {
+        self.keys[(key & 0xF) as usize]
+    }
+
+    // Presses the keys held on `other` as well
+    pub fn merge(&mut self, other: KeypadState) {
+        for (key, pressed) in self.keys.iter_mut().zip(other.keys) {
+            *key |= pressed;
+        }
+    }
+
+    // Lowest pressed key, used by FX0A
+    pub fn first_pressed(&self) -> Option<u8> {
+        self.keys.iter().position(|&k| k).map(|k| k as u8)
+    }
+}
+
+// Draws a frame whenever the framebuffer changed
+pub trait Renderer {
+    fn present(&mut self, frame: &Framebuffer);
+
+    // Whether the last frame has to be drawn again even though the
+    // framebuffer did not change, e.g. after a window resize
+    fn needs_redraw(&self) -> bool {
+        false
+    }
+
+    fn redraw(&mut self) {}
+
+    // Called once at the end of every emulated frame, drawn or not
+    fn end_frame(&mut self) {}
+
+    // Shows a ready made picture, such as a menu, instead of the emulated
+    // screen. Frontends without a screen ignore it.
+    fn show_image(&mut self, _image: &Image) {}
+
+    // Playback state such as "Paused", None when running normally
+    fn set_status(&mut self, _status: Option<&str>) {}
+
+    // An error shown over the picture until cleared, such as the assembler
+    // failing on an edited source
+    fn set_error(&mut self, _error: Option<&str>) {}
+
+    // A short notice that fades away by itself
+    fn show_message(&mut self, _message: &str) {}
+}
+
+// Requests from the user that are not CHIP-8 keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    // The window was closed or Escape pressed
+    Quit,
+    // A file was dropped on the window
+    Open(PathBuf),
+    // Go back to the ROM launcher
+    Menu,
+    // Restart the game, reloading the ROM
+    Reset,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Select,
+    ToggleFavourite,
+    NextList,
+    Pause,
+    // Runs one frame while paused
+    FrameAdvance,
+    // Held down or released
+    FastForward(bool),
+    SlowMotion,
+}
+
+// Polled once per frame, returns the keys currently held down
+pub trait InputSource {
+    fn poll(&mut self) -> KeypadState;
+
+    // Polled instead of `poll` while no frame runs, e.g. when paused
+    fn poll_idle(&mut self) -> KeypadState {
+        self.poll()
+    }
+
+    // Actions received during the last poll
+    fn actions(&mut self) -> Vec<Action> {
+        Vec::new()
+    }
+}
+
+impl<I: InputSource> InputSource for &mut I {
+    fn poll(&mut self) -> KeypadState {
+        (**self).poll()
+    }
+
+    fn poll_idle(&mut self) -> KeypadState {
+        (**self).poll_idle()
+    }
+
+    fn actions(&mut self) -> Vec<Action> {
+        (**self).actions()
+    }
+}
+
+// Told once per frame whether the sound timer is running
+pub trait AudioSink {
+    fn set_playing(&mut self, playing: bool);
+}
@@ -0,0 +1,68 @@
+use std::collections::BTreeSet;
+
+use crate::frontend::keymap::KeyMap;
+use crate::frontend::{Action, KeypadState};
+
+// Stick and trigger positions closer to the centre than this are ignored
+pub const DEADZONE: i16 = 16_000;
+
+// Key map name of a controller button, from SDL's name for it ("a",
+// "dpup", "start")
+pub fn button_name(button: &str) -> String {
+    format!("pad:{}", button)
+}
+
+// Key map names of the two directions of an axis ("leftx-", "lefty+")
+pub fn axis_names(axis: &str) -> [String; 2] {
+    [format!("pad:{}-", axis), format!("pad:{}+", axis)]
+}
+
+// Launcher and playback requests from buttons that work on any controller
+pub fn button_action(button: &str) -> Option<Action> {
+    match button {
+        "dpup" => Some(Action::Up),
+        "dpdown" => Some(Action::Down),
+        "dpleft" => Some(Action::Left),
+        "dpright" => Some(Action::Right),
+        "a" => Some(Action::Select),
+        "back" => Some(Action::Menu),
+        "start" => Some(Action::Pause),
+        _ => None,
+    }
+}
+
+// What is held down on one controller, by key map name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PadState {
+    held: BTreeSet<String>,
+}
+
+impl PadState {
+    pub fn button(&mut self, button: &str, pressed: bool) {
+        let name = button_name(button);
+        if pressed {
+            self.held.insert(name);
+        } else {
+            self.held.remove(&name);
+        }
+    }
+
+    pub fn axis(&mut self, axis: &str, value: i16) {
+        let [negative, positive] = axis_names(axis);
+        self.held.remove(&negative);
+        self.held.remove(&positive);
+        if value <= -DEADZONE {
+            self.held.insert(negative);
+        } else if value >= DEADZONE {
+            self.held.insert(positive);
+        }
+    }
+
+    pub fn keypad(&self, keymap: &KeyMap) -> KeypadState {
+        let mut keypad = KeypadState::default();
+        for key in self.held.iter().filter_map(|name| keymap.key(name)) {
+            keypad.press(key);
+        }
+        keypad
+    }
+}
@@ -0,0 +1,33 @@
+use crate::display::Framebuffer;
+use crate::frontend::{AudioSink, InputSource, KeypadState, Renderer};
+
+// Keeps the last presented frame around instead of drawing it
+#[derive(Default)]
+pub struct HeadlessRenderer {
+    pub frames: u64,
+    pub last_frame: Option<Framebuffer>,
+}
+
+impl Renderer for HeadlessRenderer {
+    fn present(&mut self, frame: &Framebuffer) {
+        self.frames += 1;
+        self.last_frame = Some(frame.clone());
+    }
+}
+
+// No keys are ever pressed
+#[derive(Default)]
+pub struct NullInput;
+
+impl InputSource for NullInput {
+    fn poll(&mut self) -> KeypadState {
+        KeypadState::default()
+    }
+}
+
+#[derive(Default)]
+pub struct NullAudio;
+
+impl AudioSink for NullAudio {
+    fn set_playing(&mut self, _playing: bool) {}
+}
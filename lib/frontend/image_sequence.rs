@@ -0,0 +1,83 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::display::Framebuffer;
+use crate::frontend::Renderer;
+use crate::render::Pipeline;
+
+// Writes every presented frame as a numbered binary PPM file
+pub struct ImageSequenceRenderer {
+    directory: PathBuf,
+    pipeline: Pipeline,
+    last_frame: Option<Framebuffer>,
+    next_frame: u64,
+    // The first write error, nothing is written after it
+    error: Option<io::Error>,
+}
+
+impl ImageSequenceRenderer {
+    pub fn new(directory: impl Into<PathBuf>, pipeline: Pipeline) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(ImageSequenceRenderer {
+            directory,
+            pipeline,
+            last_frame: None,
+            next_frame: 0,
+            error: None,
+        })
+    }
+
+    // Frames written so far
+    pub fn frames(&self) -> u64 {
+        self.next_frame
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    fn write_frame(&mut self, frame: &Framebuffer) {
+        if self.error.is_some() {
+            return;
+        }
+        let image = self.pipeline.render(frame);
+        let path = self
+            .directory
+            .join(format!("frame_{:06}.ppm", self.next_frame));
+
+        let result = fs::File::create(&path).and_then(|mut f| {
+            write!(f, "P6\n{} {}\n255\n", image.width, image.height)?;
+            f.write_all(&image.to_rgb_bytes())
+        });
+        match result {
+            Ok(()) => self.next_frame += 1,
+            Err(err) => self.error = Some(err),
+        }
+    }
+}
+
+impl Renderer for ImageSequenceRenderer {
+    fn present(&mut self, frame: &Framebuffer) {
+        self.write_frame(frame);
+        self.last_frame = Some(frame.clone());
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.pipeline.animating()
+    }
+
+    fn redraw(&mut self) {
+        if let Some(frame) = self.last_frame.take() {
+            self.write_frame(&frame);
+            self.last_frame = Some(frame);
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::frontend::NUM_KEYS;
+
+// Host keys of the classic layout, indexed by CHIP-8 key:
+//
+//     1 2 3 C        1 2 3 4
+//     4 5 6 D   <-   Q W E R
+//     7 8 9 E        A S D F
+//     A 0 B F        Z X C V
+const DEFAULT: [&str; NUM_KEYS] = [
+    "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+];
+
+// Controller inputs for the keys most games move and act with, W A S D and
+// E Q on the keyboard. See `gamepad` for their names.
+const DEFAULT_PAD: [(u8, &[&str]); 6] = [
+    (0x5, &["pad:dpup", "pad:lefty-"]),
+    (0x8, &["pad:dpdown", "pad:lefty+"]),
+    (0x7, &["pad:dpleft", "pad:leftx-"]),
+    (0x9, &["pad:dpright", "pad:leftx+"]),
+    (0x6, &["pad:a"]),
+    (0x4, &["pad:b"]),
+];
+
+// Host keys the window keeps for itself, which cannot be bound to CHIP-8
+// keys, autofire or macros
+pub const HOTKEYS: [&str; 14] = [
+    "Escape", "P", "Tab", "Pause", "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F9", "F11", "F12",
+];
+
+// The hotkey a host key name is, compared without case
+pub fn hotkey(name: &str) -> Option<&'static str> {
+    HOTKEYS
+        .into_iter()
+        .find(|hotkey| hotkey.eq_ignore_ascii_case(name))
+}
+
+// Which host keys press each CHIP-8 key. Keys are named the way SDL names
+// them ("Q", "Up", "Keypad 5"), controller inputs start with "pad:", and
+// names are compared without case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    bindings: [Vec<String>; NUM_KEYS],
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut bindings = DEFAULT.map(|name| vec![name.to_string()]);
+        for (key, names) in DEFAULT_PAD {
+            bindings[key as usize].extend(names.iter().map(|name| name.to_string()));
+        }
+        KeyMap { bindings }
+    }
+}
+
+impl KeyMap {
+    pub fn bindings(&self, key: u8) -> &[String] {
+        &self.bindings[key as usize]
+    }
+
+    pub fn bind(&mut self, key: u8, names: Vec<String>) {
+        self.bindings[key as usize] = names;
+    }
+
+    // The CHIP-8 key a host key presses
+    pub fn key(&self, name: &str) -> Option<u8> {
+        (0..NUM_KEYS as u8).find(|&key| {
+            self.bindings(key)
+                .iter()
+                .any(|bound| bound.eq_ignore_ascii_case(name))
+        })
+    }
+}
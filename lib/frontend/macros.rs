@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use crate::frontend::{KeypadState, NUM_KEYS};
+
+// Frames an autofired key stays pressed, then released
+pub const AUTOFIRE_RATE: u32 = 2;
+
+// Longest a single macro step can be repeated, a minute
+const MAX_REPEAT: usize = 3600;
+
+// A sequence of CHIP-8 key presses, one step per frame. Each step lists the
+// keys pressed during that frame joined with '+', and can be repeated for
+// several frames with '*':
+//
+//     ["4", "", "5+6*3"]   # 4, nothing, then 5 and 6 for three frames
+//
+// An empty step or "-" presses nothing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Macro {
+    // As written in the config file
+    steps: Vec<String>,
+    frames: Vec<KeypadState>,
+}
+
+fn parse_step(step: &str) -> Result<(KeypadState, usize), String> {
+    let invalid = || {
+        format!(
+            "invalid macro step '{}', expected keys such as \"5+6*3\"",
+            step
+        )
+    };
+
+    let (keys, count) = match step.split_once('*') {
+        Some((keys, count)) => {
+            let count = count.trim().parse::<usize>().map_err(|_| invalid())?;
+            if !(1..=MAX_REPEAT).contains(&count) {
+                return Err(format!(
+                    "{} is out of range 1..{} in macro step '{}'",
+                    count, MAX_REPEAT, step
+                ));
+            }
+            (keys, count)
+        }
+        None => (step, 1),
+    };
+
+    let mut keypad = KeypadState::default();
+    let keys = keys.trim();
+    if !keys.is_empty() && keys != "-" {
+        for key in keys.split('+') {
+            let key = key.trim();
+            match u8::from_str_radix(key, 16) {
+                Ok(key) if key < NUM_KEYS as u8 => keypad.press(key),
+                _ => return Err(invalid()),
+            }
+        }
+    }
+    Ok((keypad, count))
+}
+
+impl Macro {
+    pub fn parse(steps: &[String]) -> Result<Macro, String> {
+        let mut frames = Vec::new();
+        for step in steps {
+            let (keypad, count) = parse_step(step)?;
+            frames.extend(std::iter::repeat_n(keypad, count));
+        }
+        Ok(Macro {
+            steps: steps.to_vec(),
+            frames,
+        })
+    }
+
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+
+    // In frames
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+// What host keys do besides pressing a CHIP-8 key: start macros, and toggle
+// autofire of CHIP-8 keys. Host keys are named like in `KeyMap`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroMap {
+    macros: BTreeMap<String, Macro>,
+    autofire: [Vec<String>; NUM_KEYS],
+    pub autofire_rate: u32,
+}
+
+impl Default for MacroMap {
+    fn default() -> Self {
+        MacroMap {
+            macros: BTreeMap::new(),
+            autofire: Default::default(),
+            autofire_rate: AUTOFIRE_RATE,
+        }
+    }
+}
+
+impl MacroMap {
+    pub fn bind_macro(&mut self, name: &str, steps: Macro) {
+        self.macros.insert(name.to_string(), steps);
+    }
+
+    // Host keys toggling autofire of a CHIP-8 key
+    pub fn bind_autofire(&mut self, key: u8, names: Vec<String>) {
+        self.autofire[key as usize] = names;
+    }
+
+    pub fn macros(&self) -> &BTreeMap<String, Macro> {
+        &self.macros
+    }
+
+    pub fn autofire(&self, key: u8) -> &[String] {
+        &self.autofire[key as usize]
+    }
+
+    fn find_macro(&self, name: &str) -> Option<(&String, &Macro)> {
+        self.macros
+            .iter()
+            .find(|(bound, _)| bound.eq_ignore_ascii_case(name))
+    }
+}
+
+// Plays the macros and autofire of a `MacroMap` over the keys held each
+// frame. It works on keypad states, so input movies record its result.
+#[derive(Debug, Clone, Default)]
+pub struct Macros {
+    map: MacroMap,
+    // Keys with autofire turned on
+    autofire: KeypadState,
+    // Frames each key has been held for
+    held: [u32; NUM_KEYS],
+    // Macros being played, by name, with their next frame
+    running: Vec<(String, usize)>,
+}
+
+impl Macros {
+    pub fn new(map: MacroMap) -> Self {
+        Macros {
+            map,
+            ..Macros::default()
+        }
+    }
+
+    // A host key went down. Starts its macro over and toggles autofire of
+    // the keys it is bound to, returning a notice for each toggle.
+    pub fn host_key(&mut self, name: &str) -> Vec<String> {
+        if let Some((bound, _)) = self.map.find_macro(name) {
+            let bound = bound.clone();
+            self.running.retain(|(running, _)| *running != bound);
+            self.running.push((bound, 0));
+        }
+
+        let mut notices = Vec::new();
+        for key in 0..NUM_KEYS as u8 {
+            let toggles = self
+                .map
+                .autofire(key)
+                .iter()
+                .any(|bound| bound.eq_ignore_ascii_case(name));
+            if !toggles {
+                continue;
+            }
+            if self.autofire.is_pressed(key) {
+                self.autofire.release(key);
+                notices.push(format!("Autofire {:X} off", key));
+            } else {
+                self.autofire.press(key);
+                notices.push(format!("Autofire {:X} on", key));
+            }
+        }
+        notices
+    }
+
+    // The keys pressed this frame given the ones `held` down, moving the
+    // macros and autofire on by a frame. Autofired keys are pressed for
+    // `autofire_rate` frames then released as long as they are held.
+    pub fn apply(&mut self, held: KeypadState) -> KeypadState {
+        let rate = self.map.autofire_rate.max(1);
+        let mut keypad = KeypadState::default();
+        for key in 0..NUM_KEYS as u8 {
+            let frames = &mut self.held[key as usize];
+            if !held.is_pressed(key) {
+                *frames = 0;
+                continue;
+            }
+            if !self.autofire.is_pressed(key) || (*frames / rate).is_multiple_of(2) {
+                keypad.press(key);
+            }
+            *frames += 1;
+        }
+
+        let map = &self.map;
+        self.running.retain_mut(|(name, next)| {
+            let Some(steps) = map.macros.get(name) else {
+                return false;
+            };
+            if let Some(frame) = steps.frames.get(*next) {
+                keypad.merge(*frame);
+            }
+            *next += 1;
+            *next < steps.len()
+        });
+        keypad
+    }
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::frontend::{Action, InputSource, KeypadState, NUM_KEYS};
+
+// Input movies are text files with one line per frame holding the held keys
+// as a 16 bit hex mask (bit N is key N), optionally followed by how many
+// frames the line lasts. Everything after a '#' is a comment:
+//
+//     0000 60   # wait one second
+//     0020 5    # hold key 5
+//     0000
+fn to_mask(keypad: &KeypadState) -> u16 {
+    (0..NUM_KEYS as u8)
+        .filter(|&k| keypad.is_pressed(k))
+        .fold(0, |mask, k| mask | (1 << k))
+}
+
+fn from_mask(mask: u16) -> KeypadState {
+    let mut keypad = KeypadState::default();
+    for k in 0..NUM_KEYS as u8 {
+        if mask & (1 << k) != 0 {
+            keypad.press(k);
+        }
+    }
+    keypad
+}
+
+// Plays back an input movie, no keys are held once it is over
+#[derive(Default)]
+pub struct MoviePlayer {
+    frames: Vec<KeypadState>,
+    next: usize,
+}
+
+impl MoviePlayer {
+    pub fn parse(text: &str) -> Result<MoviePlayer, String> {
+        let mut frames = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let mask = fields.next().unwrap();
+            let mask = u16::from_str_radix(mask, 16)
+                .map_err(|_| format!("line {}: invalid key mask '{}'", number + 1, mask))?;
+            let count = match fields.next() {
+                Some(count) => count
+                    .parse::<usize>()
+                    .map_err(|_| format!("line {}: invalid frame count '{}'", number + 1, count))?,
+                None => 1,
+            };
+
+            frames.extend(std::iter::repeat_n(from_mask(mask), count));
+        }
+
+        Ok(MoviePlayer { frames, next: 0 })
+    }
+
+    pub fn load(path: &Path) -> Result<MoviePlayer, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+        MoviePlayer::parse(&text)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next >= self.frames.len()
+    }
+}
+
+impl InputSource for MoviePlayer {
+    fn poll(&mut self) -> KeypadState {
+        let keypad = self.frames.get(self.next).copied().unwrap_or_default();
+        self.next += 1;
+        keypad
+    }
+}
+
+// Wraps another input source and writes every polled frame to a movie file
+pub struct MovieRecorder<I: InputSource> {
+    inner: I,
+    out: BufWriter<File>,
+    last: Option<(u16, usize)>,
+    // The first write error, nothing is written after it
+    error: Option<io::Error>,
+}
+
+impl<I: InputSource> MovieRecorder<I> {
+    pub fn new(inner: I, path: &Path) -> io::Result<Self> {
+        Ok(MovieRecorder {
+            inner,
+            out: BufWriter::new(File::create(path)?),
+            last: None,
+            error: None,
+        })
+    }
+
+    fn write_run(&mut self) -> io::Result<()> {
+        match self.last {
+            Some((mask, 1)) => writeln!(self.out, "{:04x}", mask)?,
+            Some((mask, count)) => writeln!(self.out, "{:04x} {}", mask, count)?,
+            None => {}
+        }
+        self.out.flush()
+    }
+
+    pub fn finish(mut self) -> io::Result<I> {
+        if let Some(err) = self.error.take() {
+            return Err(err);
+        }
+        self.write_run()?;
+        Ok(self.inner)
+    }
+}
+
+impl<I: InputSource> InputSource for MovieRecorder<I> {
+    fn poll(&mut self) -> KeypadState {
+        let keypad = self.inner.poll();
+        let mask = to_mask(&keypad);
+
+        match self.last {
+            Some((last, count)) if last == mask => self.last = Some((last, count + 1)),
+            _ => {
+                if self.error.is_none() {
+                    self.error = self.write_run().err();
+                }
+                self.last = Some((mask, 1));
+            }
+        }
+
+        keypad
+    }
+
+    // Frames that do not run are left out of the movie
+    fn poll_idle(&mut self) -> KeypadState {
+        self.inner.poll_idle()
+    }
+
+    fn actions(&mut self) -> Vec<Action> {
+        self.inner.actions()
+    }
+}
@@ -0,0 +1,84 @@
+use std::io;
+
+use crate::capture::recording::Recorder;
+use crate::display::Framebuffer;
+use crate::frontend::Renderer;
+use crate::image::Image;
+use crate::render::Pipeline;
+
+// Wraps another renderer and records what it is shown
+pub struct RecordingRenderer<R: Renderer> {
+    inner: R,
+    pipeline: Pipeline,
+    recorder: Recorder,
+    last_frame: Option<Framebuffer>,
+    // A frame was shown during this emulated frame
+    presented: bool,
+    // The first capture error, nothing is captured after it
+    error: Option<io::Error>,
+}
+
+impl<R: Renderer> RecordingRenderer<R> {
+    pub fn new(inner: R, pipeline: Pipeline, recorder: Recorder) -> Self {
+        RecordingRenderer {
+            inner,
+            pipeline,
+            recorder,
+            last_frame: None,
+            presented: false,
+            error: None,
+        }
+    }
+
+    fn capture(&mut self, frame: &Framebuffer) {
+        let image = self.pipeline.render(frame);
+        if self.error.is_none() {
+            self.error = self.recorder.capture(&image).err();
+        }
+    }
+
+    pub fn finish(self) -> io::Result<R> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.recorder.finish()?;
+        Ok(self.inner)
+    }
+}
+
+impl<R: Renderer> Renderer for RecordingRenderer<R> {
+    fn present(&mut self, frame: &Framebuffer) {
+        self.inner.present(frame);
+        self.capture(frame);
+        self.last_frame = Some(frame.clone());
+        self.presented = true;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.inner.needs_redraw()
+    }
+
+    fn redraw(&mut self) {
+        self.inner.redraw();
+    }
+
+    fn show_image(&mut self, image: &Image) {
+        self.inner.show_image(image);
+    }
+
+    fn end_frame(&mut self) {
+        // Keep the persistence filter running on unchanged frames, once
+        // per emulated frame
+        if !self.presented
+            && self.pipeline.animating()
+            && let Some(frame) = self.last_frame.take()
+        {
+            self.capture(&frame);
+            self.last_frame = Some(frame);
+        }
+        self.presented = false;
+
+        self.inner.end_frame();
+        self.recorder.tick();
+    }
+}
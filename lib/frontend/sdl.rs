@@ -0,0 +1,929 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::Instant,
+};
+
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::controller::GameController;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{FullscreenType, Window, WindowContext};
+use sdl2::{EventPump, GameControllerSubsystem, Sdl};
+
+use crate::capture;
+use crate::capture::recording::{RecordFormat, Recorder};
+use crate::display::{Framebuffer, HEIGHT, WIDTH};
+use crate::frontend::gamepad::{self, PadState};
+use crate::frontend::keymap::KeyMap;
+use crate::frontend::macros::{MacroMap, Macros};
+use crate::frontend::{Action, AudioSink, InputSource, KeypadState, Renderer, TONE_HZ, VOLUME};
+use crate::graphics::Protocol;
+use crate::image::Image;
+use crate::palette::Palette;
+use crate::render::Pipeline;
+use crate::render::crt::CrtSettings;
+use crate::render::keypad;
+use crate::render::overlay::{FrameRate, Overlay};
+use crate::render::persistence::PersistenceMode;
+use crate::render::scaler::Scaler;
+
+// Texels per CHIP-8 pixel when the CRT filter is on, scanlines need a few
+// rows per pixel to look right
+const CRT_SCALE: usize = 4;
+
+const TITLE: &str = "Chip 8 Emulator";
+
+// Window pixels per CHIP-8 pixel of a new window, unless `scale` says
+// otherwise
+const DEFAULT_SCALE: usize = 20;
+
+// Held down to fast-forward, it picks the list in the launcher
+const FAST_FORWARD_KEY: Keycode = Keycode::Tab;
+
+// Mouse events SDL makes up from touches, which are handled on their own
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+
+pub struct SdlFrontend {
+    _sdl_context: Sdl,
+    pub renderer: SdlRenderer,
+    pub input: SdlInput,
+    pub audio: SdlAudio,
+}
+
+pub struct SdlOptions {
+    // Initial window size, in window pixels per CHIP-8 pixel
+    pub scale: usize,
+    // Only scale the picture by whole multiples, letterboxing the rest
+    pub integer_scaling: bool,
+    pub fullscreen: bool,
+    pub palette: Palette,
+    pub persistence: Option<PersistenceMode>,
+    pub scaler: Scaler,
+    pub crt: Option<CrtSettings>,
+    pub keymap: KeyMap,
+    pub macros: MacroMap,
+    // Frame rate and speed in a corner of the window
+    pub osd: bool,
+    // Screenshots show the on-screen display as well
+    pub screenshot_osd: bool,
+    // A clickable keypad next to the game
+    pub keypad: bool,
+    // The buzzer, a volume of 0 mutes it
+    pub tone_hz: f32,
+    pub volume: f32,
+    // Where the screenshot and recording hotkeys save their files
+    pub capture_dir: PathBuf,
+    pub record_format: RecordFormat,
+    // Draw in the terminal instead of a window, at `terminal_scale`
+    // terminal pixels per CHIP-8 pixel
+    pub terminal: Option<Protocol>,
+    pub terminal_scale: usize,
+}
+
+impl Default for SdlOptions {
+    fn default() -> Self {
+        SdlOptions {
+            scale: DEFAULT_SCALE,
+            integer_scaling: true,
+            fullscreen: false,
+            palette: Palette::default(),
+            persistence: None,
+            scaler: Scaler::Nearest,
+            crt: None,
+            keymap: KeyMap::default(),
+            macros: MacroMap::default(),
+            osd: false,
+            screenshot_osd: false,
+            keypad: false,
+            tone_hz: TONE_HZ,
+            volume: VOLUME,
+            capture_dir: PathBuf::from("."),
+            record_format: RecordFormat::Gif,
+            terminal: None,
+            terminal_scale: 4,
+        }
+    }
+}
+
+impl Default for SdlFrontend {
+    fn default() -> Self {
+        SdlFrontend::new(SdlOptions::default())
+    }
+}
+
+impl SdlFrontend {
+    pub fn new(options: SdlOptions) -> Self {
+        let sdl_context = sdl2::init().unwrap();
+        let video_subsystem = sdl_context
+            .video()
+            .expect("Cannot initialize video subsystem!");
+
+        // The keypad is as wide as the picture is tall
+        let width = if options.keypad {
+            WIDTH + HEIGHT
+        } else {
+            WIDTH
+        };
+        let mut window = video_subsystem
+            .window(
+                TITLE,
+                (width * options.scale) as u32,
+                (HEIGHT * options.scale) as u32,
+            )
+            .position_centered()
+            .resizable()
+            .build()
+            .unwrap();
+
+        if options.fullscreen {
+            window
+                .set_fullscreen(FullscreenType::Desktop)
+                .expect("Cannot switch to fullscreen!");
+        }
+
+        let mut canvas = window.into_canvas().build().unwrap();
+
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        canvas.present();
+
+        let event_pump = sdl_context.event_pump().unwrap();
+        // Playing without controllers is fine
+        let controllers = sdl_context.game_controller().ok();
+        let mut pipeline = Pipeline::new(options.palette, options.persistence);
+        pipeline.scaler = options.scaler;
+        if options.crt.is_some() {
+            pipeline.scale = (CRT_SCALE / options.scaler.factor()).max(1);
+            pipeline.crt = options.crt;
+        }
+
+        let state = Rc::new(WindowState {
+            dirty: Cell::new(false),
+            screenshot: Cell::new(false),
+            toggle_recording: Cell::new(false),
+            pipeline: RefCell::new(pipeline),
+            overlay: RefCell::new(Overlay::default()),
+            osd: Cell::new(options.osd),
+            keypad: Cell::new(options.keypad),
+            pressed: Cell::new(KeypadState::default()),
+            view: Cell::new(None),
+        });
+
+        SdlFrontend {
+            input: SdlInput {
+                event_pump,
+                keypad: KeypadState::default(),
+                keymap: options.keymap,
+                macros: Macros::new(options.macros),
+                clicked: None,
+                touches: HashMap::new(),
+                controllers,
+                pads: HashMap::new(),
+                actions: Vec::new(),
+                window: canvas.window().clone(),
+                state: Rc::clone(&state),
+            },
+            renderer: SdlRenderer {
+                texture_creator: canvas.texture_creator(),
+                canvas,
+                texture: None,
+                integer_scaling: options.integer_scaling,
+                scale: options.scale,
+                capture_dir: options.capture_dir,
+                record_format: options.record_format,
+                recorder: None,
+                state,
+                last_frame: None,
+                last_image: None,
+                advanced: false,
+                frame_rate: FrameRate::default(),
+                screenshot_osd: options.screenshot_osd,
+                rom_name: None,
+                status: None,
+            },
+            audio: SdlAudio::new(&sdl_context, options.tone_hz, options.volume),
+            _sdl_context: sdl_context,
+        }
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or(path.display().to_string(), |name| {
+        name.to_string_lossy().into_owned()
+    })
+}
+
+// Shared between SdlInput, which handles the window hotkeys and events, and
+// SdlRenderer
+struct WindowState {
+    // The window got resized, exposed or restyled and must be drawn again
+    dirty: Cell<bool>,
+    // A screenshot of the next drawn frame was requested
+    screenshot: Cell<bool>,
+    // Start or stop recording on the next drawn frame
+    toggle_recording: Cell<bool>,
+    pipeline: RefCell<Pipeline>,
+    // Drawn over the picture in the window only
+    overlay: RefCell<Overlay>,
+    // Whether the frame rate is shown
+    osd: Cell<bool>,
+    // Whether the keypad is shown next to the game
+    keypad: Cell<bool>,
+    // Keys held down as the game sees them, lit up on the keypad
+    pressed: Cell<KeypadState>,
+    // Where the picture with the keypad went in the window and its size,
+    // None while the keypad is not shown
+    view: Cell<Option<(Rect, usize, usize)>>,
+}
+
+impl WindowState {
+    fn message(&self, text: &str) {
+        self.overlay.borrow_mut().message(text, Instant::now());
+        self.dirty.set(true);
+    }
+}
+
+// Uploads each frame into a streaming texture and lets SDL scale it to the
+// window, keeping the aspect ratio
+pub struct SdlRenderer {
+    canvas: Canvas<Window>,
+    texture_creator: TextureCreator<WindowContext>,
+    texture: Option<(Texture, usize, usize)>,
+    integer_scaling: bool,
+    scale: usize,
+    capture_dir: PathBuf,
+    record_format: RecordFormat,
+    recorder: Option<Recorder>,
+    state: Rc<WindowState>,
+    last_frame: Option<Framebuffer>,
+    last_image: Option<Image>,
+    // The persistence filter already moved on during this emulated frame
+    advanced: bool,
+    frame_rate: FrameRate,
+    screenshot_osd: bool,
+    // Shown in the window title
+    rom_name: Option<String>,
+    status: Option<String>,
+}
+
+impl SdlRenderer {
+    fn upload(&mut self, image: &Image) {
+        let stale = match &self.texture {
+            Some((_, width, height)) => *width != image.width || *height != image.height,
+            None => true,
+        };
+
+        if stale {
+            let texture = self
+                .texture_creator
+                .create_texture_streaming(
+                    PixelFormatEnum::RGB24,
+                    image.width as u32,
+                    image.height as u32,
+                )
+                .expect("Cannot create the screen texture!");
+
+            if let Some((old, _, _)) = self.texture.replace((texture, image.width, image.height)) {
+                // SAFETY: the old texture is not referenced anywhere else
+                unsafe { old.destroy() };
+            }
+        }
+
+        let (texture, _, _) = self.texture.as_mut().unwrap();
+        texture
+            .with_lock(None, |buffer: &mut [u8], pitch: usize| {
+                let row_len = image.width * 3;
+                let bytes = image.to_rgb_bytes();
+                for (y, row) in bytes.chunks(row_len).enumerate() {
+                    buffer[y * pitch..y * pitch + row_len].copy_from_slice(row);
+                }
+            })
+            .expect("Cannot upload the screen texture!");
+    }
+
+    // Largest rectangle with the texture aspect ratio that fits the window
+    fn destination(&self, width: usize, height: usize) -> Rect {
+        let (out_w, out_h) = self.canvas.output_size().unwrap();
+        let scale_x = out_w as f32 / width as f32;
+        let scale_y = out_h as f32 / height as f32;
+
+        let mut scale = scale_x.min(scale_y);
+        if self.integer_scaling && scale >= 1.0 {
+            scale = scale.floor();
+        }
+
+        let dst_w = (width as f32 * scale) as u32;
+        let dst_h = (height as f32 * scale) as u32;
+
+        Rect::new(
+            ((out_w - dst_w) / 2) as i32,
+            ((out_h - dst_h) / 2) as i32,
+            dst_w,
+            dst_h,
+        )
+    }
+
+    fn render(&mut self) {
+        // Only games get the keypad, not the menu
+        let with_keypad = self.last_frame.is_some() && self.state.keypad.get();
+        if let Some(frame) = &self.last_frame {
+            let image = self.state.pipeline.borrow_mut().draw(frame);
+            let mut shown = {
+                let now = Instant::now();
+                let mut overlay = self.state.overlay.borrow_mut();
+                overlay.expire(now);
+                (!overlay.is_empty()).then(|| overlay.draw(&image, now))
+            };
+            if with_keypad {
+                let pipeline = self.state.pipeline.borrow();
+                shown = Some(keypad::draw(
+                    shown.as_ref().unwrap_or(&image),
+                    self.state.pressed.get(),
+                    pipeline.palette.background(),
+                    pipeline.palette.foreground(),
+                ));
+            }
+            self.upload(shown.as_ref().unwrap_or(&image));
+
+            let factor = self.capture_factor(&image);
+            if let Some(recorder) = &mut self.recorder
+                && let Err(err) = recorder.capture(&image.scale(factor))
+            {
+                // Stops the recording rather than failing on every frame
+                let path = recorder.path().to_path_buf();
+                self.state
+                    .message(&format!("Cannot record {}: {}", file_name(&path), err));
+                self.recorder = None;
+            }
+
+            self.last_image = Some(image);
+        }
+
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+
+        self.state.view.set(None);
+        if let Some((texture, width, height)) = &self.texture {
+            let dst = self.destination(*width, *height);
+            self.canvas
+                .copy(texture, None, dst)
+                .expect("Cannot copy the screen texture!");
+            if with_keypad {
+                self.state.view.set(Some((dst, *width, *height)));
+            }
+        }
+
+        self.canvas.present();
+    }
+
+    // How much to enlarge `image`, drawn from the last frame, so that
+    // captures get `scale` image pixels per CHIP-8 pixel
+    fn capture_factor(&self, image: &Image) -> usize {
+        let width = self.last_frame.as_ref().map_or(WIDTH, Framebuffer::width);
+        (self.scale / (image.width / width).max(1)).max(1)
+    }
+
+    // Saves the picture as shown, at `scale` image pixels per CHIP-8 pixel
+    fn save_screenshot(&self) {
+        let Some(image) = &self.last_image else {
+            return;
+        };
+        let image = if self.screenshot_osd {
+            &self.state.overlay.borrow().draw(image, Instant::now())
+        } else {
+            image
+        };
+
+        let factor = self.capture_factor(image);
+        let path = capture::timestamped_path(&self.capture_dir, "screenshot", "png");
+
+        match capture::save_png(&image.scale(factor), &path) {
+            Ok(()) => self.state.message(&format!("Saved {}", file_name(&path))),
+            Err(err) => self
+                .state
+                .message(&format!("Cannot save {}: {}", file_name(&path), err)),
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        match self.recorder.take() {
+            Some(recorder) => {
+                let path = recorder.path().to_path_buf();
+                match recorder.finish() {
+                    Ok(()) => self.state.message(&format!("Saved {}", file_name(&path))),
+                    Err(err) => {
+                        self.state
+                            .message(&format!("Cannot save {}: {}", file_name(&path), err))
+                    }
+                }
+            }
+            None => {
+                let path = capture::timestamped_path(
+                    &self.capture_dir,
+                    "recording",
+                    self.record_format.extension(),
+                );
+                self.state
+                    .message(&format!("Recording {}", file_name(&path)));
+                self.recorder = Some(Recorder::new(path, self.record_format));
+            }
+        }
+    }
+}
+
+impl SdlRenderer {
+    // Saves the recording started with the hotkey, if there is one
+    pub fn stop_recording(&mut self) {
+        if self.recorder.is_some() {
+            self.toggle_recording();
+        }
+    }
+
+    // Names the game in the window title, None in the menu
+    pub fn set_rom_name(&mut self, name: Option<&str>) {
+        self.rom_name = name.map(str::to_string);
+        self.update_title();
+    }
+
+    fn update_title(&mut self) {
+        let title = [
+            Some(TITLE),
+            self.rom_name.as_deref(),
+            self.status.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" - ");
+        // Titles never contain a nul byte
+        let _ = self.canvas.window_mut().set_title(&title);
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.state.pipeline.borrow_mut().palette = palette;
+        self.state.dirty.set(true);
+    }
+}
+
+impl Renderer for SdlRenderer {
+    fn present(&mut self, frame: &Framebuffer) {
+        self.state.pipeline.borrow_mut().advance(frame);
+        self.advanced = true;
+        self.last_frame = Some(frame.clone());
+        self.redraw();
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.state.dirty.get()
+            || self.state.screenshot.get()
+            || self.state.toggle_recording.get()
+            || self.state.overlay.borrow().animating()
+    }
+
+    fn redraw(&mut self) {
+        self.state.dirty.set(false);
+        if self.state.toggle_recording.take() {
+            self.toggle_recording();
+        }
+        self.render();
+
+        if self.state.screenshot.take() {
+            self.save_screenshot();
+        }
+    }
+
+    fn end_frame(&mut self) {
+        // Fading pixels move on once per emulated frame, also while the
+        // game leaves the screen alone, but not while it is paused
+        if !self.advanced
+            && let Some(frame) = &self.last_frame
+            && self.state.pipeline.borrow().animating()
+        {
+            self.state.pipeline.borrow_mut().advance(frame);
+            self.render();
+        }
+        self.advanced = false;
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.tick();
+        }
+        if self.state.osd.get() && self.frame_rate.frame(Instant::now()) {
+            self.state.overlay.borrow_mut().stats = self.frame_rate.text();
+            self.state.dirty.set(true);
+        }
+    }
+
+    fn show_image(&mut self, image: &Image) {
+        // The next game starts from a blank screen rather than the last one
+        self.last_frame = None;
+        self.upload(image);
+        self.render();
+    }
+
+    fn set_status(&mut self, status: Option<&str>) {
+        self.status = status.map(str::to_string);
+        self.update_title();
+
+        self.state.overlay.borrow_mut().status = status.map(str::to_string);
+        self.state.dirty.set(true);
+    }
+
+    fn set_error(&mut self, error: Option<&str>) {
+        let error = error.map(str::to_string);
+        let mut overlay = self.state.overlay.borrow_mut();
+        if overlay.error != error {
+            overlay.error = error;
+            self.state.dirty.set(true);
+        }
+    }
+
+    fn show_message(&mut self, message: &str) {
+        self.state.message(message);
+    }
+}
+
+pub struct SdlInput {
+    event_pump: EventPump,
+    keypad: KeypadState,
+    keymap: KeyMap,
+    macros: Macros,
+    // Keypad keys held with the mouse and with each finger
+    clicked: Option<u8>,
+    touches: HashMap<i64, u8>,
+    controllers: Option<GameControllerSubsystem>,
+    // Open controllers by joystick instance id
+    pads: HashMap<u32, (GameController, PadState)>,
+    actions: Vec<Action>,
+    window: Window,
+    state: Rc<WindowState>,
+}
+
+impl SdlInput {
+    // Per ROM bindings, for keyboards and controllers alike
+    pub fn set_keymap(&mut self, keymap: KeyMap) {
+        self.keymap = keymap;
+    }
+
+    // Per ROM macros and autofire, turning autofire off again
+    pub fn set_macros(&mut self, macros: MacroMap) {
+        self.macros = Macros::new(macros);
+    }
+
+    // Starts the macros of a host key and toggles the autofire it is bound to
+    fn host_key(&mut self, name: &str) {
+        for notice in self.macros.host_key(name) {
+            self.state.message(&notice);
+        }
+    }
+
+    // Lights up the keys on the keypad
+    fn show_pressed(&self, keypad: KeypadState) {
+        if self.state.pressed.replace(keypad) != keypad && self.state.keypad.get() {
+            self.state.dirty.set(true);
+        }
+    }
+
+    // Controllers are opened as they are plugged in, SDL reports the ones
+    // present at start the same way
+    fn open_controller(&mut self, index: u32) {
+        let Some(controllers) = &self.controllers else {
+            return;
+        };
+        match controllers.open(index) {
+            Ok(controller) => {
+                self.state
+                    .message(&format!("Controller connected: {}", controller.name()));
+                self.pads
+                    .insert(controller.instance_id(), (controller, PadState::default()));
+            }
+            Err(err) => self
+                .state
+                .message(&format!("Cannot open controller {}: {}", index, err)),
+        }
+    }
+
+    fn close_controller(&mut self, id: u32) {
+        if let Some((controller, _)) = self.pads.remove(&id) {
+            self.state
+                .message(&format!("Controller disconnected: {}", controller.name()));
+        }
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let next = match self.window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+
+        // Not every video driver supports fullscreen, keep running windowed
+        if self.window.set_fullscreen(next).is_ok() {
+            self.state.dirty.set(true);
+        }
+    }
+
+    fn cycle_palette(&mut self) {
+        let mut pipeline = self.state.pipeline.borrow_mut();
+        pipeline.palette = pipeline.palette.next();
+        self.state
+            .message(&format!("Palette {}", pipeline.palette.to_spec()));
+    }
+
+    fn toggle_keypad(&mut self) {
+        self.state.keypad.set(!self.state.keypad.get());
+        self.clicked = None;
+        self.touches.clear();
+        self.state.dirty.set(true);
+    }
+
+    // Keypad key at (x, y) in drawable pixels, which differ from window
+    // coordinates on high density displays
+    fn key_at(&self, x: f32, y: f32) -> Option<u8> {
+        let (dst, width, height) = self.state.view.get()?;
+        let x = (x - dst.x() as f32) * width as f32 / dst.width() as f32;
+        let y = (y - dst.y() as f32) * height as f32 / dst.height() as f32;
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        keypad::key_at(width, height, x as usize, y as usize)
+    }
+
+    fn click(&self, x: i32, y: i32) -> Option<u8> {
+        let (window_w, window_h) = self.window.size();
+        let (out_w, out_h) = self.window.drawable_size();
+        self.key_at(
+            x as f32 * out_w as f32 / window_w as f32,
+            y as f32 * out_h as f32 / window_h as f32,
+        )
+    }
+
+    // Fingers are reported from 0 to 1 across the window
+    fn touch(&self, x: f32, y: f32) -> Option<u8> {
+        let (out_w, out_h) = self.window.drawable_size();
+        self.key_at(x * out_w as f32, y * out_h as f32)
+    }
+
+    fn toggle_osd(&mut self) {
+        let osd = !self.state.osd.get();
+        self.state.osd.set(osd);
+        if !osd {
+            self.state.overlay.borrow_mut().stats = None;
+        }
+        self.state.dirty.set(true);
+    }
+}
+
+fn action(keycode: Keycode) -> Option<Action> {
+    match keycode {
+        Keycode::F1 => Some(Action::Menu),
+        Keycode::F2 => Some(Action::Reset),
+        Keycode::Up => Some(Action::Up),
+        Keycode::Down => Some(Action::Down),
+        Keycode::Left => Some(Action::Left),
+        Keycode::Right => Some(Action::Right),
+        Keycode::PageUp => Some(Action::PageUp),
+        Keycode::PageDown => Some(Action::PageDown),
+        Keycode::Return | Keycode::KpEnter => Some(Action::Select),
+        Keycode::Space => Some(Action::ToggleFavourite),
+        Keycode::Tab => Some(Action::NextList),
+        Keycode::Pause | Keycode::F5 => Some(Action::Pause),
+        Keycode::F6 => Some(Action::FrameAdvance),
+        Keycode::F7 => Some(Action::SlowMotion),
+        _ => None,
+    }
+}
+
+impl SdlInput {
+    // Handles the pending events, returning the keys held down. Hotkeys
+    // are listed in `keymap::HOTKEYS` so that they are not bound.
+    fn poll_events(&mut self) -> KeypadState {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        self.actions.clear();
+
+        for event in events {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => self.actions.push(Action::Quit),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F11),
+                    repeat: false,
+                    ..
+                } => self.toggle_fullscreen(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::P),
+                    repeat: false,
+                    ..
+                } => self.cycle_palette(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    repeat: false,
+                    ..
+                } => self.toggle_osd(),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    repeat: false,
+                    ..
+                } => self.toggle_keypad(),
+                Event::MouseButtonDown {
+                    which,
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if which != TOUCH_MOUSE_ID => self.clicked = self.click(x, y),
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } => self.clicked = None,
+                Event::FingerDown {
+                    finger_id, x, y, ..
+                } => {
+                    if let Some(key) = self.touch(x, y) {
+                        self.touches.insert(finger_id, key);
+                    }
+                }
+                Event::FingerUp { finger_id, .. } => {
+                    self.touches.remove(&finger_id);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => self.state.screenshot.set(true),
+                Event::KeyDown {
+                    keycode: Some(Keycode::F9),
+                    repeat: false,
+                    ..
+                } => self.state.toggle_recording.set(true),
+                Event::ControllerDeviceAdded { which, .. } => self.open_controller(which),
+                Event::ControllerDeviceRemoved { which, .. } => self.close_controller(which),
+                Event::ControllerButtonDown { which, button, .. } => {
+                    if let Some((_, pad)) = self.pads.get_mut(&which) {
+                        pad.button(&button.string(), true);
+                    }
+                    self.host_key(&gamepad::button_name(&button.string()));
+                    if let Some(action) = gamepad::button_action(&button.string()) {
+                        self.actions.push(action);
+                    }
+                }
+                Event::ControllerButtonUp { which, button, .. } => {
+                    if let Some((_, pad)) = self.pads.get_mut(&which) {
+                        pad.button(&button.string(), false);
+                    }
+                }
+                Event::ControllerAxisMotion {
+                    which, axis, value, ..
+                } => {
+                    if let Some((_, pad)) = self.pads.get_mut(&which) {
+                        pad.axis(&axis.string(), value);
+                    }
+                }
+                Event::DropFile { filename, .. } => {
+                    self.actions.push(Action::Open(PathBuf::from(filename)))
+                }
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..) | WindowEvent::Exposed,
+                    ..
+                } => self.state.dirty.set(true),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat,
+                    ..
+                } => {
+                    if let Some(key) = self.keymap.key(&keycode.name()) {
+                        self.keypad.press(key);
+                    }
+                    if let Some(action) = action(keycode) {
+                        self.actions.push(action);
+                    }
+                    if !repeat {
+                        self.host_key(&keycode.name());
+                    }
+                    if keycode == FAST_FORWARD_KEY && !repeat {
+                        self.actions.push(Action::FastForward(true));
+                    }
+                }
+                Event::KeyUp {
+                    keycode: Some(keycode),
+                    ..
+                } => {
+                    if let Some(key) = self.keymap.key(&keycode.name()) {
+                        self.keypad.release(key);
+                    }
+                    if keycode == FAST_FORWARD_KEY {
+                        self.actions.push(Action::FastForward(false));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut keypad = self.keypad;
+        for (_, pad) in self.pads.values() {
+            keypad.merge(pad.keypad(&self.keymap));
+        }
+        for &key in self.clicked.iter().chain(self.touches.values()) {
+            keypad.press(key);
+        }
+        keypad
+    }
+}
+
+impl InputSource for SdlInput {
+    // Macros and autofire only move on with the frames that run
+    fn poll(&mut self) -> KeypadState {
+        let held = self.poll_events();
+        let keypad = self.macros.apply(held);
+        self.show_pressed(keypad);
+        keypad
+    }
+
+    fn poll_idle(&mut self) -> KeypadState {
+        let keypad = self.poll_events();
+        self.show_pressed(keypad);
+        keypad
+    }
+
+    fn actions(&mut self) -> Vec<Action> {
+        std::mem::take(&mut self.actions)
+    }
+}
+
+struct SquareWave {
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            *x = if self.phase <= 0.5 {
+                self.volume
+            } else {
+                -self.volume
+            };
+            self.phase = (self.phase + self.phase_inc) % 1.0;
+        }
+    }
+}
+
+// Plays a square wave while the sound timer is running. Machines without an
+// audio device simply stay silent.
+pub struct SdlAudio {
+    device: Option<AudioDevice<SquareWave>>,
+    playing: bool,
+}
+
+impl SdlAudio {
+    fn new(sdl_context: &Sdl, tone_hz: f32, volume: f32) -> Self {
+        let desired = AudioSpecDesired {
+            freq: Some(44_100),
+            channels: Some(1),
+            samples: None,
+        };
+
+        let device = sdl_context.audio().ok().and_then(|audio| {
+            audio
+                .open_playback(None, &desired, |spec| SquareWave {
+                    phase_inc: tone_hz / spec.freq as f32,
+                    phase: 0.0,
+                    volume,
+                })
+                .ok()
+        });
+
+        SdlAudio {
+            device,
+            playing: false,
+        }
+    }
+}
+
+impl AudioSink for SdlAudio {
+    fn set_playing(&mut self, playing: bool) {
+        if playing == self.playing {
+            return;
+        }
+        self.playing = playing;
+
+        if let Some(device) = &self.device {
+            if playing {
+                device.resume();
+            } else {
+                device.pause();
+            }
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::display::Framebuffer;
+use crate::frontend::Renderer;
+use crate::graphics::TerminalGraphics;
+
+pub enum TerminalMode {
+    Text,
+    Graphics(TerminalGraphics),
+}
+
+pub struct TerminalRenderer {
+    pub mode: TerminalMode,
+}
+
+impl TerminalRenderer {
+    pub fn new(mode: TerminalMode) -> Self {
+        TerminalRenderer { mode }
+    }
+}
+
+impl Renderer for TerminalRenderer {
+    fn present(&mut self, frame: &Framebuffer) {
+        match &self.mode {
+            TerminalMode::Text => {
+                print!("\x1b[H");
+                frame.display_terminal();
+            }
+            TerminalMode::Graphics(graphics) => frame.display_graphics(graphics),
+        }
+    }
+}
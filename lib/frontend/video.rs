@@ -0,0 +1,131 @@
+use std::io::{self, Seek, Write};
+
+use crate::capture::video::VideoWriter;
+use crate::capture::wav::{SAMPLE_RATE, WavWriter};
+use crate::display::{Display, Framebuffer};
+use crate::frontend::{AudioSink, Renderer};
+use crate::image::Image;
+use crate::render::Pipeline;
+
+const FPS: u32 = 60;
+
+// Wraps another renderer and writes exactly one video frame per emulated
+// frame, whether the screen changed or not
+pub struct VideoRenderer<R: Renderer, W: Write> {
+    inner: R,
+    pipeline: Pipeline,
+    writer: VideoWriter<W>,
+    frame: Framebuffer,
+    // The first write error, nothing is written after it
+    error: Option<io::Error>,
+}
+
+impl<R: Renderer, W: Write> VideoRenderer<R, W> {
+    pub fn new(inner: R, pipeline: Pipeline, writer: VideoWriter<W>) -> Self {
+        VideoRenderer {
+            inner,
+            pipeline,
+            writer,
+            frame: Display::new(),
+            error: None,
+        }
+    }
+
+    pub fn finish(self) -> io::Result<R> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.writer.finish()?;
+        Ok(self.inner)
+    }
+}
+
+impl<R: Renderer, W: Write> Renderer for VideoRenderer<R, W> {
+    fn present(&mut self, frame: &Framebuffer) {
+        self.inner.present(frame);
+        self.frame = frame.clone();
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.inner.needs_redraw()
+    }
+
+    fn redraw(&mut self) {
+        self.inner.redraw();
+    }
+
+    fn show_image(&mut self, image: &Image) {
+        self.inner.show_image(image);
+    }
+
+    fn end_frame(&mut self) {
+        self.inner.end_frame();
+
+        if self.error.is_none() {
+            let image = self.pipeline.render(&self.frame);
+            self.error = self.writer.write_frame(&image).err();
+        }
+    }
+}
+
+// Writes the buzzer to a WAV file, one frame worth of samples per call to
+// `set_playing` so the track stays in sync with the video
+pub struct WavAudio<W: Write + Seek> {
+    writer: WavWriter<W>,
+    tone_hz: f32,
+    // 0 to 1, 0 mutes it
+    volume: f32,
+    phase: f32,
+    frames: u64,
+    error: Option<io::Error>,
+}
+
+impl<W: Write + Seek> WavAudio<W> {
+    pub fn new(out: W, tone_hz: f32, volume: f32) -> io::Result<Self> {
+        Ok(WavAudio {
+            writer: WavWriter::new(out)?,
+            tone_hz,
+            volume,
+            phase: 0.0,
+            frames: 0,
+            error: None,
+        })
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.writer.finish()
+    }
+}
+
+impl<W: Write + Seek> AudioSink for WavAudio<W> {
+    fn set_playing(&mut self, playing: bool) {
+        // Derived from the absolute sample position so 44100 / 60 rounding
+        // does not drift
+        let start = self.frames * SAMPLE_RATE as u64 / FPS as u64;
+        self.frames += 1;
+        let end = self.frames * SAMPLE_RATE as u64 / FPS as u64;
+
+        let amplitude = self.volume * i16::MAX as f32;
+        let samples: Vec<i16> = (start..end)
+            .map(|_| {
+                if !playing {
+                    return 0;
+                }
+                let sample = if self.phase <= 0.5 {
+                    amplitude
+                } else {
+                    -amplitude
+                };
+                self.phase = (self.phase + self.tone_hz / SAMPLE_RATE as f32) % 1.0;
+                sample as i16
+            })
+            .collect();
+
+        if self.error.is_none() {
+            self.error = self.writer.write_samples(&samples).err();
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use crate::image::{Image, Rgb};
+
+// Chunk size (in base64 characters) mandated by the kitty graphics protocol
+const KITTY_CHUNK: usize = 4096;
+// Sixel only addresses a 256 colour register table
+const SIXEL_MAX_COLORS: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Sixel,
+    Kitty,
+}
+
+impl Protocol {
+    pub fn parse(name: &str) -> Result<Protocol, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "sixel" => Ok(Protocol::Sixel),
+            "kitty" => Ok(Protocol::Kitty),
+            _ => Err(format!(
+                "unknown terminal '{}', expected sixel or kitty",
+                name
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Sixel => "sixel",
+            Protocol::Kitty => "kitty",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TerminalGraphics {
+    pub protocol: Protocol,
+    pub scale: usize,
+    pub foreground: Rgb,
+    pub background: Rgb,
+}
+
+impl TerminalGraphics {
+    pub fn new(protocol: Protocol) -> Self {
+        TerminalGraphics {
+            protocol,
+            scale: 4,
+            foreground: Rgb::WHITE,
+            background: Rgb::BLACK,
+        }
+    }
+
+    // Encodes a 1-bit framebuffer as a complete escape sequence
+    pub fn encode(&self, data: &[bool], width: usize, height: usize) -> String {
+        let image = Image::from_pixels(data, width, height, self.background, self.foreground)
+            .scale(self.scale);
+
+        match self.protocol {
+            Protocol::Sixel => encode_sixel(&image),
+            Protocol::Kitty => encode_kitty(&image),
+        }
+    }
+}
+
+fn percent(channel: u8) -> u32 {
+    (channel as u32 * 100 + 127) / 255
+}
+
+fn push_sixel_run(out: &mut String, sixel: u8, run: usize) {
+    let c = (0x3F + sixel) as char;
+    if run > 3 {
+        out.push_str(&format!("!{}{}", run, c));
+    } else {
+        for _ in 0..run {
+            out.push(c);
+        }
+    }
+}
+
+pub fn encode_sixel(image: &Image) -> String {
+    let mut palette: Vec<Rgb> = Vec::new();
+    let mut indices: Vec<usize> = Vec::with_capacity(image.pixels.len());
+
+    for pixel in &image.pixels {
+        let idx = match palette.iter().position(|c| c == pixel) {
+            Some(idx) => idx,
+            None => {
+                assert!(
+                    palette.len() < SIXEL_MAX_COLORS,
+                    "Error: Sixel images are limited to {} colours",
+                    SIXEL_MAX_COLORS
+                );
+                palette.push(*pixel);
+                palette.len() - 1
+            }
+        };
+        indices.push(idx);
+    }
+
+    let mut out = String::from("\x1bPq");
+    out.push_str(&format!("\"1;1;{};{}", image.width, image.height));
+
+    for (idx, color) in palette.iter().enumerate() {
+        out.push_str(&format!(
+            "#{};2;{};{};{}",
+            idx,
+            percent(color.0),
+            percent(color.1),
+            percent(color.2)
+        ));
+    }
+
+    for band in (0..image.height).step_by(6) {
+        let rows = (image.height - band).min(6);
+        let mut first = true;
+
+        for color in 0..palette.len() {
+            let column = |x: usize| -> u8 {
+                (0..rows)
+                    .filter(|&r| indices[(band + r) * image.width + x] == color)
+                    .fold(0, |bits, r| bits | (1 << r))
+            };
+
+            if (0..image.width).all(|x| column(x) == 0) {
+                continue;
+            }
+
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            out.push_str(&format!("#{}", color));
+
+            let mut current = column(0);
+            let mut run = 0;
+            for x in 0..image.width {
+                let sixel = column(x);
+                if sixel == current {
+                    run += 1;
+                } else {
+                    push_sixel_run(&mut out, current, run);
+                    current = sixel;
+                    run = 1;
+                }
+            }
+            push_sixel_run(&mut out, current, run);
+        }
+
+        if band + 6 < image.height {
+            out.push('-');
+        }
+    }
+
+    out.push_str("\x1b\\");
+    out
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        out.push(ALPHABET[(n >> 18) as usize & 0x3F] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 0x3F] as char);
+        if chunk.len() > 1 {
+            out.push(ALPHABET[(n >> 6) as usize & 0x3F] as char);
+        } else {
+            out.push('=');
+        }
+        if chunk.len() > 2 {
+            out.push(ALPHABET[n as usize & 0x3F] as char);
+        } else {
+            out.push('=');
+        }
+    }
+
+    out
+}
+
+pub fn encode_kitty(image: &Image) -> String {
+    let payload = base64(&image.to_rgb_bytes());
+    let chunks: Vec<&str> = payload
+        .as_bytes()
+        .chunks(KITTY_CHUNK)
+        .map(|c| std::str::from_utf8(c).unwrap())
+        .collect();
+
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        if i == 0 {
+            out.push_str(&format!(
+                "\x1b_Ga=T,f=24,s={},v={},q=2,m={};{}\x1b\\",
+                image.width, image.height, more, chunk
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+
+    out
+}
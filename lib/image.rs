@@ -0,0 +1,81 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0x00, 0x00, 0x00);
+    pub const WHITE: Rgb = Rgb(0xFF, 0xFF, 0xFF);
+}
+
+// Plain RGB raster, the common output of every frontend that needs real pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, fill: Rgb) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    // Maps a 1-bit framebuffer (row major) to colours
+    pub fn from_pixels(data: &[bool], width: usize, height: usize, off: Rgb, on: Rgb) -> Self {
+        Image {
+            width,
+            height,
+            pixels: data[..width * height]
+                .iter()
+                .map(|&lit| if lit { on } else { off })
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Rgb {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    // Pixels falling outside the image are dropped
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for py in y..(y + height).min(self.height) {
+            for px in x..(x + width).min(self.width) {
+                self.set(px, py, color);
+            }
+        }
+    }
+
+    // Copies `image` with its top left corner at (x, y)
+    pub fn blit(&mut self, image: &Image, x: usize, y: usize) {
+        for sy in 0..image.height.min(self.height.saturating_sub(y)) {
+            for sx in 0..image.width.min(self.width.saturating_sub(x)) {
+                self.set(x + sx, y + sy, image.get(sx, sy));
+            }
+        }
+    }
+
+    // Nearest neighbour upscaling by an integer factor
+    pub fn scale(&self, factor: usize) -> Image {
+        let factor = factor.max(1);
+        let mut scaled = Image::new(self.width * factor, self.height * factor, Rgb::BLACK);
+
+        for y in 0..scaled.height {
+            for x in 0..scaled.width {
+                scaled.set(x, y, self.get(x / factor, y / factor));
+            }
+        }
+
+        scaled
+    }
+
+    pub fn to_rgb_bytes(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|p| [p.0, p.1, p.2]).collect()
+    }
+}
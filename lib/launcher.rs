@@ -0,0 +1,333 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::chip::Chip;
+use crate::display::{Display, HEIGHT, WIDTH};
+use crate::font::{self, LINE_HEIGHT};
+use crate::frontend::{Action, KeypadState};
+use crate::image::{Image, Rgb};
+use crate::palette::Palette;
+
+pub mod library;
+
+use library::{LibraryState, RomEntry};
+
+// The menu is drawn at this size and scaled to the window like a frame
+pub const MENU_WIDTH: usize = 384;
+pub const MENU_HEIGHT: usize = 192;
+
+const MARGIN: usize = 4;
+const LIST_TOP: usize = 20;
+const LIST_WIDTH: usize = 240;
+const THUMBNAIL_SCALE: usize = 2;
+const PANEL_LEFT: usize = MENU_WIDTH - WIDTH * THUMBNAIL_SCALE - MARGIN;
+const FOOTER_TOP: usize = MENU_HEIGHT - LINE_HEIGHT - 1;
+const VISIBLE_ROWS: usize = (FOOTER_TOP - 2 - LIST_TOP) / LINE_HEIGHT;
+// How long a ROM runs before its screen is used as a thumbnail
+const THUMBNAIL_FRAMES: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    All,
+    Favourites,
+    Recent,
+}
+
+impl View {
+    fn name(&self) -> &'static str {
+        match self {
+            View::All => "All",
+            View::Favourites => "Favourites",
+            View::Recent => "Recent",
+        }
+    }
+
+    fn next(&self) -> View {
+        match self {
+            View::All => View::Favourites,
+            View::Favourites => View::Recent,
+            View::Recent => View::All,
+        }
+    }
+}
+
+// Runs a ROM for a couple of seconds without input. Broken ROMs stop on an
+// instruction that cannot run, they simply get no thumbnail.
+fn thumbnail(path: &Path) -> Option<Display> {
+    let mut chip = Chip::new();
+    chip.load_program(path.to_str()?).ok()?;
+    for _ in 0..THUMBNAIL_FRAMES {
+        chip.try_run_frame(KeypadState::default()).ok()?;
+    }
+    Some(chip.framebuffer().clone())
+}
+
+// ROM picker drawn into an image, driven by `Action`s
+pub struct Launcher {
+    directory: PathBuf,
+    library: Vec<RomEntry>,
+    state: LibraryState,
+    state_path: Option<PathBuf>,
+    view: View,
+    selected: usize,
+    scroll: usize,
+    thumbnails: HashMap<PathBuf, Option<Display>>,
+    palette: Palette,
+    // The last library error, shown in place of the key help
+    error: Option<String>,
+}
+
+impl Launcher {
+    pub fn new(directory: &Path, palette: Palette) -> Launcher {
+        let state_path = library::state_path();
+        let state = state_path
+            .as_deref()
+            .and_then(|path| LibraryState::load(path).ok())
+            .unwrap_or_default();
+
+        Launcher::with_state(directory, palette, state, state_path)
+    }
+
+    // `state_path` None keeps the favourites and recent list in memory only
+    pub fn with_state(
+        directory: &Path,
+        palette: Palette,
+        state: LibraryState,
+        state_path: Option<PathBuf>,
+    ) -> Launcher {
+        let (library, error) = match library::scan(directory) {
+            Ok(library) => (library, None),
+            Err(err) => (
+                Vec::new(),
+                Some(format!("Cannot read {}: {}", directory.display(), err)),
+            ),
+        };
+
+        Launcher {
+            directory: directory.to_path_buf(),
+            library,
+            state,
+            state_path,
+            view: View::All,
+            selected: 0,
+            scroll: 0,
+            thumbnails: HashMap::new(),
+            palette,
+            error,
+        }
+    }
+
+    pub fn view(&self) -> View {
+        self.view
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn state(&self) -> &LibraryState {
+        &self.state
+    }
+
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+    }
+
+    // Entries of the current view, favourites and recent ROMs may live
+    // outside the library directory
+    pub fn entries(&self) -> Vec<RomEntry> {
+        let lookup = |path: &PathBuf| {
+            self.library
+                .iter()
+                .find(|entry| entry.path == *path)
+                .cloned()
+                .or_else(|| RomEntry::new(path).ok())
+        };
+
+        match self.view {
+            View::All => self.library.clone(),
+            View::Favourites => self.state.favourites.iter().filter_map(lookup).collect(),
+            View::Recent => self.state.recent.iter().filter_map(lookup).collect(),
+        }
+    }
+
+    pub fn selected(&self) -> Option<RomEntry> {
+        self.entries().into_iter().nth(self.selected)
+    }
+
+    fn save_state(&mut self) {
+        if let Some(path) = &self.state_path
+            && let Err(err) = self.state.save(path)
+        {
+            self.error = Some(format!("Cannot save {}: {}", path.display(), err));
+        }
+    }
+
+    // Remembers a ROM that was started, from the menu or not
+    pub fn played(&mut self, path: &Path) {
+        self.state.played(path);
+        self.save_state();
+    }
+
+    fn select(&mut self, index: usize) {
+        let count = self.entries().len();
+        self.selected = index.min(count.saturating_sub(1));
+
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + VISIBLE_ROWS {
+            self.scroll = self.selected + 1 - VISIBLE_ROWS;
+        }
+    }
+
+    // Returns the ROM to start once one is picked
+    pub fn handle(&mut self, action: Action) -> Option<PathBuf> {
+        match action {
+            Action::Up => self.select(self.selected.saturating_sub(1)),
+            Action::Down => self.select(self.selected + 1),
+            Action::PageUp | Action::Left => {
+                self.select(self.selected.saturating_sub(VISIBLE_ROWS))
+            }
+            Action::PageDown | Action::Right => self.select(self.selected + VISIBLE_ROWS),
+            Action::NextList => {
+                self.view = self.view.next();
+                self.scroll = 0;
+                self.select(0);
+            }
+            Action::ToggleFavourite => {
+                if let Some(entry) = self.selected() {
+                    self.state.toggle_favourite(&entry.path);
+                    self.save_state();
+                    self.select(self.selected);
+                }
+            }
+            Action::Select => return self.selected().map(|entry| entry.path),
+            _ => {}
+        }
+        None
+    }
+
+    fn thumbnail(&mut self, path: &Path) -> Option<Image> {
+        let frame = self
+            .thumbnails
+            .entry(path.to_path_buf())
+            .or_insert_with(|| thumbnail(path))
+            .as_ref()?;
+
+        let image = Image::from_pixels(
+            &frame.data,
+            WIDTH,
+            HEIGHT,
+            self.palette.background(),
+            self.palette.foreground(),
+        );
+        Some(image.scale(THUMBNAIL_SCALE))
+    }
+
+    pub fn render(&mut self) -> Image {
+        let [background, foreground, dim, _] = self.palette.colors;
+        let mut image = Image::new(MENU_WIDTH, MENU_HEIGHT, background);
+        let text = |image: &mut Image, x, y, s: &str, color: Rgb| {
+            font::draw_text(image, x, y, s, color, 1)
+        };
+
+        // Header with the lists, the current one highlighted
+        text(&mut image, MARGIN, MARGIN, "ROM LIBRARY", foreground);
+        let mut x = MENU_WIDTH - MARGIN;
+        for view in [View::Recent, View::Favourites, View::All] {
+            x -= font::text_width(view.name(), 1) + 12;
+            let color = if view == self.view { foreground } else { dim };
+            text(&mut image, x, MARGIN, view.name(), color);
+        }
+        image.fill_rect(MARGIN, LIST_TOP - 5, MENU_WIDTH - 2 * MARGIN, 1, dim);
+
+        let entries = self.entries();
+        if entries.is_empty() {
+            let message = match self.view {
+                View::All => format!("No ROMs in {}", self.directory.display()),
+                View::Favourites => "No favourites yet, Space marks one".to_string(),
+                View::Recent => "Nothing played yet".to_string(),
+            };
+            text(
+                &mut image,
+                MARGIN,
+                LIST_TOP,
+                &font::truncate(&message, 60),
+                dim,
+            );
+        }
+
+        let max_chars = (LIST_WIDTH - MARGIN) / font::ADVANCE - 2;
+        for (row, entry) in entries
+            .iter()
+            .enumerate()
+            .skip(self.scroll)
+            .take(VISIBLE_ROWS)
+        {
+            let y = LIST_TOP + (row - self.scroll) * LINE_HEIGHT;
+            let color = if row == self.selected {
+                image.fill_rect(MARGIN - 1, y - 1, LIST_WIDTH, LINE_HEIGHT, foreground);
+                background
+            } else {
+                foreground
+            };
+
+            let marker = if self.state.is_favourite(&entry.path) {
+                "*"
+            } else {
+                " "
+            };
+            let line = format!("{} {}", marker, font::truncate(&entry.title, max_chars));
+            text(&mut image, MARGIN, y, &line, color);
+        }
+
+        // Details of the selected ROM
+        if let Some(entry) = entries.get(self.selected) {
+            let (width, height) = (WIDTH * THUMBNAIL_SCALE, HEIGHT * THUMBNAIL_SCALE);
+            image.fill_rect(PANEL_LEFT - 1, LIST_TOP - 1, width + 2, height + 2, dim);
+            match self.thumbnail(&entry.path) {
+                Some(thumbnail) => image.blit(&thumbnail, PANEL_LEFT, LIST_TOP),
+                None => image.fill_rect(PANEL_LEFT, LIST_TOP, width, height, background),
+            }
+
+            let chars = width / font::ADVANCE;
+            let file_name = entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let details = [
+                (entry.title.clone(), foreground),
+                (entry.authors.join(", "), dim),
+                (entry.platform.clone().unwrap_or_default(), dim),
+                (file_name, dim),
+            ];
+
+            let mut y = LIST_TOP + height + 6;
+            for (line, color) in details.iter().filter(|(line, _)| !line.is_empty()) {
+                text(
+                    &mut image,
+                    PANEL_LEFT,
+                    y,
+                    &font::truncate(line, chars),
+                    *color,
+                );
+                y += LINE_HEIGHT;
+            }
+        }
+
+        image.fill_rect(MARGIN, FOOTER_TOP - 3, MENU_WIDTH - 2 * MARGIN, 1, dim);
+        let footer = match &self.error {
+            Some(error) => (font::truncate(error, 60), foreground),
+            None => (
+                "Enter play  Space favourite  Tab list  F1 menu  Esc quit".to_string(),
+                dim,
+            ),
+        };
+        text(&mut image, MARGIN, FOOTER_TOP, &footer.0, footer.1);
+
+        image
+    }
+}
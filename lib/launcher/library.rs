@@ -0,0 +1,142 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::chip::MAX_PROGRAM_SIZE;
+use crate::config;
+use crate::database::Database;
+
+const MAX_RECENT: usize = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RomEntry {
+    pub path: PathBuf,
+    pub title: String,
+    pub authors: Vec<String>,
+    pub platform: Option<String>,
+}
+
+impl RomEntry {
+    // Titles come from the ROM database, unknown ROMs use their file name
+    pub fn new(path: &Path) -> io::Result<RomEntry> {
+        let program = fs::read(path)?;
+        let info = Database::bundled().lookup(&program);
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Ok(RomEntry {
+            path: canonical(path),
+            title: info.map_or(file_name, |info| info.title.clone()),
+            authors: info.map(|info| info.authors.clone()).unwrap_or_default(),
+            platform: info.and_then(|info| info.platform.clone()),
+        })
+    }
+}
+
+// So that the same ROM reached through different paths is one entry
+pub fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// Every ROM in a directory, sorted by title
+pub fn scan(directory: &Path) -> io::Result<Vec<RomEntry>> {
+    let mut entries = Vec::new();
+
+    for file in fs::read_dir(directory)? {
+        let path = file?.path();
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        // Skip anything that cannot be a ROM, such as notes or images
+        let cartridge = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+        if !metadata.is_file() || (!cartridge && metadata.len() > MAX_PROGRAM_SIZE as u64) {
+            continue;
+        }
+        if let Ok(entry) = RomEntry::new(&path) {
+            entries.push(entry);
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.title.to_ascii_lowercase());
+    Ok(entries)
+}
+
+// Where the favourites and recent list are kept between runs
+pub fn state_path() -> Option<PathBuf> {
+    Some(config::config_dir()?.join("library"))
+}
+
+// Favourite and recently played ROMs, saved as one "favourite <path>" or
+// "recent <path>" line each, most recent first
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LibraryState {
+    pub favourites: Vec<PathBuf>,
+    pub recent: Vec<PathBuf>,
+}
+
+impl LibraryState {
+    pub fn parse(text: &str) -> LibraryState {
+        let mut state = LibraryState::default();
+
+        for line in text.lines() {
+            match line.split_once(' ') {
+                Some(("favourite", path)) => state.favourites.push(PathBuf::from(path)),
+                Some(("recent", path)) => state.recent.push(PathBuf::from(path)),
+                _ => {}
+            }
+        }
+
+        state
+    }
+
+    pub fn to_text(&self) -> String {
+        let favourites = self.favourites.iter().map(|p| ("favourite", p));
+        let recent = self.recent.iter().map(|p| ("recent", p));
+
+        favourites
+            .chain(recent)
+            .map(|(kind, path)| format!("{} {}\n", kind, path.display()))
+            .collect()
+    }
+
+    // A missing file is an empty state
+    pub fn load(path: &Path) -> io::Result<LibraryState> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(LibraryState::parse(&text)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(LibraryState::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.to_text())
+    }
+
+    pub fn is_favourite(&self, path: &Path) -> bool {
+        self.favourites.iter().any(|p| p == path)
+    }
+
+    pub fn toggle_favourite(&mut self, path: &Path) {
+        match self.favourites.iter().position(|p| p == path) {
+            Some(index) => {
+                self.favourites.remove(index);
+            }
+            None => self.favourites.push(path.to_path_buf()),
+        }
+    }
+
+    pub fn played(&mut self, path: &Path) {
+        let path = canonical(path);
+        self.recent.retain(|p| *p != path);
+        self.recent.insert(0, path);
+        self.recent.truncate(MAX_RECENT);
+    }
+}
@@ -1,2 +1,16 @@
+pub mod assembler;
+pub mod capture;
+pub mod cartridge;
 pub mod chip;
-pub mod display;
\ No newline at end of file
+pub mod config;
+pub mod database;
+pub mod disassembler;
+pub mod display;
+pub mod font;
+pub mod frontend;
+pub mod graphics;
+pub mod image;
+pub mod launcher;
+pub mod palette;
+pub mod render;
+pub mod watch;
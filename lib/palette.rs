@@ -0,0 +1,196 @@
+use crate::image::Rgb;
+
+// Colours are ordered like Octo's: background, first plane, second plane and
+// both planes. Plain CHIP-8 only ever uses the first two.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub name: String,
+    pub colors: [Rgb; 4],
+}
+
+const BUILTIN: &[(&str, [Rgb; 4])] = &[
+    (
+        "classic",
+        [
+            Rgb(0x00, 0x00, 0x00),
+            Rgb(0xFF, 0xFF, 0xFF),
+            Rgb(0xAA, 0xAA, 0xAA),
+            Rgb(0x55, 0x55, 0x55),
+        ],
+    ),
+    (
+        "amber",
+        [
+            Rgb(0x1A, 0x10, 0x00),
+            Rgb(0xFF, 0xB0, 0x00),
+            Rgb(0xB3, 0x6B, 0x00),
+            Rgb(0xFF, 0xD8, 0x80),
+        ],
+    ),
+    (
+        "green",
+        [
+            Rgb(0x00, 0x14, 0x00),
+            Rgb(0x33, 0xFF, 0x33),
+            Rgb(0x1A, 0x99, 0x1A),
+            Rgb(0xB0, 0xFF, 0xB0),
+        ],
+    ),
+    (
+        "lcd",
+        [
+            Rgb(0x9B, 0xBC, 0x0F),
+            Rgb(0x0F, 0x38, 0x0F),
+            Rgb(0x30, 0x62, 0x30),
+            Rgb(0x8B, 0xAC, 0x0F),
+        ],
+    ),
+    (
+        "octo",
+        [
+            Rgb(0x99, 0x66, 0x00),
+            Rgb(0xFF, 0xCC, 0x00),
+            Rgb(0xFF, 0x66, 0x00),
+            Rgb(0x66, 0x22, 0x00),
+        ],
+    ),
+    (
+        "high-contrast",
+        [
+            Rgb(0x00, 0x00, 0x00),
+            Rgb(0xFF, 0xFF, 0x00),
+            Rgb(0x00, 0xFF, 0xFF),
+            Rgb(0xFF, 0xFF, 0xFF),
+        ],
+    ),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::named("classic").unwrap()
+    }
+}
+
+// Accepts "#RRGGBB", "RRGGBB" and the short "#RGB" form
+pub fn parse_hex(hex: &str) -> Option<Rgb> {
+    let hex = hex.trim().trim_start_matches('#');
+    if !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+
+    match hex.len() {
+        6 => Some(Rgb(
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        3 => Some(Rgb(
+            channel(&hex[0..1])? * 0x11,
+            channel(&hex[1..2])? * 0x11,
+            channel(&hex[2..3])? * 0x11,
+        )),
+        _ => None,
+    }
+}
+
+impl Palette {
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|(name, _)| *name)
+    }
+
+    pub fn named(name: &str) -> Option<Palette> {
+        BUILTIN
+            .iter()
+            .find(|(builtin, _)| builtin.eq_ignore_ascii_case(name))
+            .map(|(name, colors)| Palette {
+                name: name.to_string(),
+                colors: *colors,
+            })
+    }
+
+    // Builds a palette from 2 to 4 hex colours. Missing plane colours are
+    // derived from the background and foreground.
+    pub fn from_hex_list(colors: &[&str]) -> Result<Palette, String> {
+        if !(2..=4).contains(&colors.len()) {
+            return Err(format!(
+                "a palette needs 2 to 4 colours, got {}",
+                colors.len()
+            ));
+        }
+
+        let mut parsed = Vec::new();
+        for color in colors {
+            parsed.push(parse_hex(color).ok_or(format!("invalid hex colour '{}'", color))?);
+        }
+
+        let background = parsed[0];
+        let foreground = parsed[1];
+        let mix = Rgb(
+            ((background.0 as u16 + foreground.0 as u16) / 2) as u8,
+            ((background.1 as u16 + foreground.1 as u16) / 2) as u8,
+            ((background.2 as u16 + foreground.2 as u16) / 2) as u8,
+        );
+
+        Ok(Palette {
+            name: "custom".to_string(),
+            colors: [
+                background,
+                foreground,
+                *parsed.get(2).unwrap_or(&mix),
+                *parsed.get(3).unwrap_or(&foreground),
+            ],
+        })
+    }
+
+    // Either a built-in name or a comma separated list of hex colours
+    pub fn parse(spec: &str) -> Result<Palette, String> {
+        if let Some(palette) = Palette::named(spec) {
+            return Ok(palette);
+        }
+
+        if spec.contains(',') || spec.starts_with('#') {
+            let colors: Vec<&str> = spec.split(',').collect();
+            return Palette::from_hex_list(&colors);
+        }
+
+        Err(format!(
+            "unknown palette '{}', expected one of: {}",
+            spec,
+            Palette::names().collect::<Vec<_>>().join(", ")
+        ))
+    }
+
+    // The inverse of `parse`
+    pub fn to_spec(&self) -> String {
+        if Palette::named(&self.name).is_some_and(|named| named == *self) {
+            return self.name.clone();
+        }
+        let colors: Vec<String> = self
+            .colors
+            .iter()
+            .map(|c| format!("#{:02X}{:02X}{:02X}", c.0, c.1, c.2))
+            .collect();
+        colors.join(",")
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgb {
+        self.colors[1]
+    }
+
+    // The built-in palette after this one, wrapping around
+    pub fn next(&self) -> Palette {
+        let names: Vec<&str> = Palette::names().collect();
+        let next = match names.iter().position(|n| *n == self.name) {
+            Some(idx) => names[(idx + 1) % names.len()],
+            None => names[0],
+        };
+
+        Palette::named(next).unwrap()
+    }
+}
@@ -0,0 +1,100 @@
+use crate::display::{Framebuffer, HEIGHT, WIDTH};
+use crate::image::{Image, Rgb};
+use crate::palette::Palette;
+
+pub mod crt;
+pub mod keypad;
+pub mod overlay;
+pub mod persistence;
+pub mod scaler;
+
+use crt::CrtSettings;
+use persistence::{Persistence, PersistenceMode};
+use scaler::Scaler;
+
+fn mix(off: Rgb, on: Rgb, level: f32) -> Rgb {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * level).round() as u8;
+    Rgb(
+        channel(off.0, on.0),
+        channel(off.1, on.1),
+        channel(off.2, on.2),
+    )
+}
+
+// Software post-processing shared by every frontend producing pixels, so
+// windows, screenshots and recordings all look the same
+pub struct Pipeline {
+    pub palette: Palette,
+    pub persistence: Option<Persistence>,
+    pub scaler: Scaler,
+    // Nearest neighbour upscaling applied before the CRT effects
+    pub scale: usize,
+    pub crt: Option<CrtSettings>,
+    // Size of the last frame drawn
+    frame_size: (usize, usize),
+}
+
+impl Pipeline {
+    pub fn new(palette: Palette, persistence: Option<PersistenceMode>) -> Self {
+        Pipeline {
+            palette,
+            persistence: persistence.map(Persistence::new),
+            scaler: Scaler::Nearest,
+            scale: 1,
+            crt: None,
+            frame_size: (WIDTH, HEIGHT),
+        }
+    }
+
+    // Size of the images `render` returns, for frames the size of the last
+    // one drawn
+    pub fn output_size(&self) -> (usize, usize) {
+        let factor = self.scaler.factor() * self.scale;
+        (self.frame_size.0 * factor, self.frame_size.1 * factor)
+    }
+
+    // Turns one emulated frame into the final image
+    pub fn render(&mut self, frame: &Framebuffer) -> Image {
+        self.advance(frame);
+        self.draw(frame)
+    }
+
+    // Moves the persistence filter on by one emulated frame
+    pub fn advance(&mut self, frame: &Framebuffer) {
+        if let Some(persistence) = &mut self.persistence {
+            persistence.step(&frame.data);
+        }
+    }
+
+    // Draws the frame the persistence filter last saw again, such as when the
+    // window is redrawn or the palette changed, without moving it on
+    pub fn draw(&mut self, frame: &Framebuffer) -> Image {
+        self.frame_size = (frame.width(), frame.height());
+        let native = self.draw_native(frame);
+        let image = self.scaler.apply(&native).scale(self.scale);
+
+        match &self.crt {
+            Some(settings) => crt::apply(&image, settings),
+            None => image,
+        }
+    }
+
+    fn draw_native(&self, frame: &Framebuffer) -> Image {
+        let (off, on) = (self.palette.background(), self.palette.foreground());
+        let (width, height) = (frame.width(), frame.height());
+
+        match self.persistence.as_ref().map(Persistence::levels) {
+            Some(levels) if levels.len() == frame.data.len() => Image {
+                width,
+                height,
+                pixels: levels.iter().map(|&l| mix(off, on, l)).collect(),
+            },
+            _ => Image::from_pixels(&frame.data, width, height, off, on),
+        }
+    }
+
+    // Frames must keep being rendered while the persistence filter fades
+    pub fn animating(&self) -> bool {
+        self.persistence.as_ref().is_some_and(|p| p.settling())
+    }
+}
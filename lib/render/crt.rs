@@ -0,0 +1,186 @@
+use crate::image::{Image, Rgb};
+
+// Strength of every effect, 0.0 disables it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrtSettings {
+    // How much darker every other row is
+    pub scanlines: f32,
+    // How much of the blurred picture is added back on top of it
+    pub bloom: f32,
+    // How much the corners are darkened
+    pub vignette: f32,
+    // Barrel distortion of the tube
+    pub curvature: f32,
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        CrtSettings {
+            scanlines: 0.35,
+            bloom: 0.25,
+            vignette: 0.3,
+            curvature: 0.08,
+        }
+    }
+}
+
+// Names of the effects, with the highest strength each one allows
+pub const EFFECTS: [(&str, f32); 4] = [
+    ("scanlines", 1.0),
+    ("bloom", 2.0),
+    ("vignette", 2.0),
+    ("curvature", 0.5),
+];
+
+impl CrtSettings {
+    // The strength of one of `EFFECTS`
+    pub fn effect_mut(&mut self, name: &str) -> Option<&mut f32> {
+        match name {
+            "scanlines" => Some(&mut self.scanlines),
+            "bloom" => Some(&mut self.bloom),
+            "vignette" => Some(&mut self.vignette),
+            "curvature" => Some(&mut self.curvature),
+            _ => None,
+        }
+    }
+
+    pub fn effect(&self, name: &str) -> Option<f32> {
+        let mut settings = *self;
+        settings.effect_mut(name).copied()
+    }
+}
+
+const BLOOM_RADIUS: usize = 2;
+
+type Buffer = Vec<[f32; 3]>;
+
+fn to_buffer(image: &Image) -> Buffer {
+    image
+        .pixels
+        .iter()
+        .map(|p| [p.0 as f32, p.1 as f32, p.2 as f32])
+        .collect()
+}
+
+fn to_image(buffer: &Buffer, width: usize, height: usize) -> Image {
+    let channel = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+    Image {
+        width,
+        height,
+        pixels: buffer
+            .iter()
+            .map(|c| Rgb(channel(c[0]), channel(c[1]), channel(c[2])))
+            .collect(),
+    }
+}
+
+// Position of a pixel centre in -1.0..1.0 on both axes
+fn normalized(x: usize, y: usize, width: usize, height: usize) -> (f32, f32) {
+    (
+        (x as f32 + 0.5) / width as f32 * 2.0 - 1.0,
+        (y as f32 + 0.5) / height as f32 * 2.0 - 1.0,
+    )
+}
+
+fn curvature(buffer: &Buffer, width: usize, height: usize, amount: f32) -> Buffer {
+    let mut out = vec![[0.0; 3]; buffer.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = normalized(x, y, width, height);
+            let factor = 1.0 + amount * (u * u + v * v);
+            let (su, sv) = (u * factor, v * factor);
+
+            if su.abs() > 1.0 || sv.abs() > 1.0 {
+                continue;
+            }
+
+            let sx = (((su + 1.0) / 2.0 * width as f32) as usize).min(width - 1);
+            let sy = (((sv + 1.0) / 2.0 * height as f32) as usize).min(height - 1);
+            out[y * width + x] = buffer[sy * width + sx];
+        }
+    }
+
+    out
+}
+
+fn scanlines(buffer: &mut Buffer, width: usize, amount: f32) {
+    for (y, row) in buffer.chunks_mut(width).enumerate() {
+        if y % 2 == 1 {
+            for pixel in row {
+                for c in pixel.iter_mut() {
+                    *c *= 1.0 - amount;
+                }
+            }
+        }
+    }
+}
+
+// Separable box blur added on top of the picture
+fn bloom(buffer: &mut Buffer, width: usize, height: usize, amount: f32) {
+    let blur = |src: &Buffer, horizontal: bool| -> Buffer {
+        let mut out = vec![[0.0; 3]; src.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 3];
+                let mut count = 0.0;
+                let (pos, len) = if horizontal { (x, width) } else { (y, height) };
+
+                for i in pos.saturating_sub(BLOOM_RADIUS)..(pos + BLOOM_RADIUS + 1).min(len) {
+                    let idx = if horizontal {
+                        y * width + i
+                    } else {
+                        i * width + x
+                    };
+                    for c in 0..3 {
+                        sum[c] += src[idx][c];
+                    }
+                    count += 1.0;
+                }
+
+                out[y * width + x] = sum.map(|s| s / count);
+            }
+        }
+        out
+    };
+
+    let blurred = blur(&blur(buffer, true), false);
+    for (pixel, glow) in buffer.iter_mut().zip(blurred) {
+        for c in 0..3 {
+            pixel[c] += glow[c] * amount;
+        }
+    }
+}
+
+fn vignette(buffer: &mut Buffer, width: usize, height: usize, amount: f32) {
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = normalized(x, y, width, height);
+            let factor = (1.0 - amount * (u * u + v * v) / 2.0).clamp(0.0, 1.0);
+            for c in buffer[y * width + x].iter_mut() {
+                *c *= factor;
+            }
+        }
+    }
+}
+
+// Runs on the CPU only, so the effects also end up in headless captures
+pub fn apply(image: &Image, settings: &CrtSettings) -> Image {
+    let (width, height) = (image.width, image.height);
+    let mut buffer = to_buffer(image);
+
+    if settings.curvature > 0.0 {
+        buffer = curvature(&buffer, width, height, settings.curvature);
+    }
+    if settings.scanlines > 0.0 {
+        scanlines(&mut buffer, width, settings.scanlines);
+    }
+    if settings.bloom > 0.0 {
+        bloom(&mut buffer, width, height, settings.bloom);
+    }
+    if settings.vignette > 0.0 {
+        vignette(&mut buffer, width, height, settings.vignette);
+    }
+
+    to_image(&buffer, width, height)
+}
@@ -0,0 +1,67 @@
+use super::mix;
+use crate::font::{self, GLYPH_HEIGHT};
+use crate::frontend::KeypadState;
+use crate::image::{Image, Rgb};
+
+// The COSMAC VIP keypad, row by row
+pub const LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+// Pictures shorter than this are scaled up first so the labels fit the keys
+pub const MIN_HEIGHT: usize = 128;
+
+// Space around each key, in parts of a key cell
+const GAP: usize = 8;
+
+// `image` scaled up to at least `MIN_HEIGHT` with the keypad on its right,
+// in a square panel as tall as the picture. The keys take the colours of
+// `off` and `on`, usually the palette ones, and the keys in `pressed` are
+// lit up.
+pub fn draw(image: &Image, pressed: KeypadState, off: Rgb, on: Rgb) -> Image {
+    let factor = MIN_HEIGHT.div_ceil(image.height).max(1);
+    let image = image.scale(factor);
+    let size = image.height;
+
+    let mut shown = Image::new(image.width + size, image.height, off);
+    shown.blit(&image, 0, 0);
+
+    let cell = size / 4;
+    let gap = (cell / GAP).max(1);
+    let text_scale = (cell / (3 * GLYPH_HEIGHT)).max(1);
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (column, &key) in keys.iter().enumerate() {
+            let x = image.width + column * cell;
+            let y = row * cell;
+            let (face, label) = if pressed.is_pressed(key) {
+                (on, off)
+            } else {
+                (mix(off, on, 0.2), on)
+            };
+            shown.fill_rect(x + gap, y + gap, cell - 2 * gap, cell - 2 * gap, face);
+
+            let text = format!("{:X}", key);
+            let text_x = x + (cell - font::text_width(&text, text_scale)) / 2;
+            let text_y = y + (cell - GLYPH_HEIGHT * text_scale) / 2;
+            font::draw_text(&mut shown, text_x, text_y, &text, label, text_scale);
+        }
+    }
+
+    shown
+}
+
+// Key under (x, y) in a picture returned by `draw`, which is `width` by
+// `height` pixels
+pub fn key_at(width: usize, height: usize, x: usize, y: usize) -> Option<u8> {
+    let left = width.checked_sub(height)?;
+    if x < left || x >= width || y >= height {
+        return None;
+    }
+
+    let cell = (height / 4).max(1);
+    let row = LAYOUT.get(y / cell)?;
+    row.get((x - left) / cell).copied()
+}
@@ -0,0 +1,214 @@
+use std::slice;
+use std::time::{Duration, Instant};
+
+use super::mix;
+use crate::chip::FRAME_DURATION;
+use crate::font::{self, ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT};
+use crate::image::{Image, Rgb};
+
+// Pictures narrower than this are scaled up before text is drawn on them,
+// so that the text stays small next to the CHIP-8 pixels
+pub const MIN_WIDTH: usize = 384;
+
+// Messages stay this long, fading out over the last second
+pub const MESSAGE_TIME: Duration = Duration::from_secs(3);
+const FADE_TIME: Duration = Duration::from_secs(1);
+const MAX_MESSAGES: usize = 4;
+
+const MARGIN: usize = 4;
+const PADDING: usize = 2;
+const BACKGROUND: Rgb = Rgb(0x10, 0x10, 0x10);
+const TEXT: Rgb = Rgb(0xFF, 0xFF, 0xFF);
+const ERROR_BACKGROUND: Rgb = Rgb(0x60, 0x10, 0x10);
+
+// Splits `text` into lines of at most `width` characters
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let chars: Vec<char> = paragraph.chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        for line in chars.chunks(width.max(1)) {
+            lines.push(line.iter().collect());
+        }
+    }
+    lines
+}
+
+// Draws `lines` in a box with its top left corner at (x, y), mixed into the
+// picture by `level` from 0 (invisible) to 1
+fn draw_box(image: &mut Image, x: usize, y: usize, lines: &[String], background: Rgb, level: f32) {
+    let width = lines
+        .iter()
+        .map(|line| font::text_width(line, 1))
+        .max()
+        .unwrap_or(0)
+        + 2 * PADDING;
+    let height = lines.len() * LINE_HEIGHT - (LINE_HEIGHT - GLYPH_HEIGHT) + 2 * PADDING;
+
+    let mut text = Image::new(width, height, background);
+    for (row, line) in lines.iter().enumerate() {
+        font::draw_text(
+            &mut text,
+            PADDING,
+            PADDING + row * LINE_HEIGHT,
+            line,
+            TEXT,
+            1,
+        );
+    }
+
+    for ty in 0..height.min(image.height.saturating_sub(y)) {
+        for tx in 0..width.min(image.width.saturating_sub(x)) {
+            let under = image.get(x + tx, y + ty);
+            image.set(x + tx, y + ty, mix(under, text.get(tx, ty), level));
+        }
+    }
+}
+
+// Emulated frames per second, counted over one second
+#[derive(Debug, Clone)]
+pub struct FrameRate {
+    since: Instant,
+    frames: u32,
+    rate: Option<u32>,
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        FrameRate {
+            since: Instant::now(),
+            frames: 0,
+            rate: None,
+        }
+    }
+}
+
+impl FrameRate {
+    // Returns whether the rate was updated
+    pub fn frame(&mut self, now: Instant) -> bool {
+        self.frames += 1;
+        let elapsed = now.duration_since(self.since);
+        if elapsed < Duration::from_secs(1) {
+            return false;
+        }
+
+        self.rate = Some((self.frames as f32 / elapsed.as_secs_f32()).round() as u32);
+        self.frames = 0;
+        self.since = now;
+        true
+    }
+
+    // Such as "60 FPS 100%", the percentage being the emulation speed
+    pub fn text(&self) -> Option<String> {
+        let rate = self.rate?;
+        let normal = 1.0 / FRAME_DURATION.as_secs_f32();
+        let speed = (rate as f32 / normal * 100.0).round();
+        Some(format!("{} FPS {}%", rate, speed))
+    }
+}
+
+// Text shown over the picture in a window. It is drawn on a copy of the
+// rendered frame, so it never reaches the framebuffer, and screenshots or
+// recordings only get it when asked for.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overlay {
+    // Frame rate and speed, top left
+    pub stats: Option<String>,
+    // Playback state such as "Paused", top right
+    pub status: Option<String>,
+    // Shown along the bottom until cleared, such as assembler errors
+    pub error: Option<String>,
+    // Short notices with when they were shown, bottom left
+    messages: Vec<(String, Instant)>,
+}
+
+impl Overlay {
+    pub fn message(&mut self, text: &str, now: Instant) {
+        self.messages.push((text.to_string(), now));
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+    }
+
+    // Forgets messages that faded out
+    pub fn expire(&mut self, now: Instant) {
+        self.messages
+            .retain(|(_, shown)| now.duration_since(*shown) < MESSAGE_TIME);
+    }
+
+    // Whether messages are still on screen and the picture has to be drawn
+    // again as they fade
+    pub fn animating(&self) -> bool {
+        !self.messages.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_none() && self.status.is_none() && self.error.is_none() && !self.animating()
+    }
+
+    // `image` scaled up to at least `MIN_WIDTH` with the overlay on top, as
+    // it looks at `now`
+    pub fn draw(&self, image: &Image, now: Instant) -> Image {
+        let factor = MIN_WIDTH.div_ceil(image.width).max(1);
+        let mut image = image.scale(factor);
+        let columns = (image.width - 2 * MARGIN - 2 * PADDING) / ADVANCE;
+
+        if let Some(stats) = &self.stats {
+            draw_box(
+                &mut image,
+                MARGIN,
+                MARGIN,
+                slice::from_ref(stats),
+                BACKGROUND,
+                0.8,
+            );
+        }
+        if let Some(status) = &self.status {
+            let width = font::text_width(status, 1) + 2 * PADDING;
+            let x = image.width.saturating_sub(width + MARGIN);
+            draw_box(
+                &mut image,
+                x,
+                MARGIN,
+                slice::from_ref(status),
+                BACKGROUND,
+                0.8,
+            );
+        }
+
+        // Along the bottom, the error below the messages
+        let mut bottom = image.height - MARGIN;
+        if let Some(error) = &self.error {
+            let max_lines = (image.height - 2 * MARGIN) / LINE_HEIGHT;
+            let mut lines = wrap(error, columns);
+            lines.truncate(max_lines);
+
+            let height = lines.len() * LINE_HEIGHT - (LINE_HEIGHT - GLYPH_HEIGHT) + 2 * PADDING;
+            bottom = bottom.saturating_sub(height);
+            image.fill_rect(
+                0,
+                bottom - MARGIN,
+                image.width,
+                height + 2 * MARGIN,
+                ERROR_BACKGROUND,
+            );
+            draw_box(&mut image, MARGIN, bottom, &lines, ERROR_BACKGROUND, 1.0);
+            bottom = bottom.saturating_sub(MARGIN);
+        }
+        for (text, shown) in self.messages.iter().rev() {
+            let age = now.saturating_duration_since(*shown);
+            let Some(left) = MESSAGE_TIME.checked_sub(age) else {
+                continue;
+            };
+            let level = (left.as_secs_f32() / FADE_TIME.as_secs_f32()).min(1.0) * 0.8;
+
+            let line = font::truncate(text, columns);
+            bottom = bottom.saturating_sub(GLYPH_HEIGHT + 2 * PADDING + 1);
+            draw_box(&mut image, MARGIN, bottom, &[line], BACKGROUND, level);
+        }
+
+        image
+    }
+}
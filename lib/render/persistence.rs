@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PersistenceMode {
+    // A pixel that turns off fades out linearly over this many frames
+    Fade(u32),
+    // A pixel is lit if it was lit in any of the last N frames
+    Blend(usize),
+}
+
+impl PersistenceMode {
+    // "fade:N" or "blend:N"
+    pub fn parse(spec: &str) -> Result<PersistenceMode, String> {
+        let (mode, frames) = spec.split_once(':').ok_or(format!(
+            "invalid persistence '{}', expected fade:N or blend:N",
+            spec
+        ))?;
+        let frames: u32 = frames
+            .parse()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or(format!("invalid persistence frame count '{}'", frames))?;
+
+        match mode {
+            "fade" => Ok(PersistenceMode::Fade(frames)),
+            "blend" => Ok(PersistenceMode::Blend(frames as usize)),
+            _ => Err(format!(
+                "invalid persistence mode '{}', expected fade or blend",
+                mode
+            )),
+        }
+    }
+
+    // The inverse of `parse`
+    pub fn to_spec(&self) -> String {
+        match self {
+            PersistenceMode::Fade(frames) => format!("fade:{}", frames),
+            PersistenceMode::Blend(frames) => format!("blend:{}", frames),
+        }
+    }
+}
+
+// Hides the flicker of XOR drawn sprites by keeping pixels lit for a while
+// after the game turned them off
+pub struct Persistence {
+    mode: PersistenceMode,
+    levels: Vec<f32>,
+    history: VecDeque<Vec<bool>>,
+}
+
+impl Persistence {
+    pub fn new(mode: PersistenceMode) -> Self {
+        Persistence {
+            mode,
+            levels: Vec::new(),
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn mode(&self) -> PersistenceMode {
+        self.mode
+    }
+
+    // Advances one frame and returns the brightness of every pixel, 0.0 to 1.0
+    pub fn apply(&mut self, data: &[bool]) -> Vec<f32> {
+        self.step(data);
+        self.levels()
+    }
+
+    // Advances one frame
+    pub fn step(&mut self, data: &[bool]) {
+        match self.mode {
+            PersistenceMode::Fade(frames) => {
+                if self.levels.len() != data.len() {
+                    self.levels = vec![0.0; data.len()];
+                }
+
+                let step = 1.0 / frames as f32;
+                for (level, &lit) in self.levels.iter_mut().zip(data) {
+                    *level = if lit { 1.0 } else { (*level - step).max(0.0) };
+                }
+            }
+            PersistenceMode::Blend(frames) => {
+                // Frames of another resolution do not blend
+                if self
+                    .history
+                    .back()
+                    .is_some_and(|last| last.len() != data.len())
+                {
+                    self.history.clear();
+                }
+                self.history.push_back(data.to_vec());
+                while self.history.len() > frames {
+                    self.history.pop_front();
+                }
+            }
+        }
+    }
+
+    // The brightness of every pixel after the last step, 0.0 to 1.0
+    pub fn levels(&self) -> Vec<f32> {
+        match self.mode {
+            PersistenceMode::Fade(_) => self.levels.clone(),
+            PersistenceMode::Blend(_) => {
+                let size = self.history.back().map_or(0, Vec::len);
+                (0..size)
+                    .map(|i| {
+                        if self.history.iter().any(|frame| frame[i]) {
+                            1.0
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    // Whether another frame would still change the output of an unchanged
+    // framebuffer
+    pub fn settling(&self) -> bool {
+        match self.mode {
+            PersistenceMode::Fade(_) => self.levels.iter().any(|&l| l > 0.0 && l < 1.0),
+            PersistenceMode::Blend(_) => self
+                .history
+                .back()
+                .is_some_and(|last| self.history.iter().any(|frame| frame != last)),
+        }
+    }
+}
@@ -0,0 +1,382 @@
+use crate::image::{Image, Rgb};
+
+// Pixel art upscalers. Most only compare colours so they keep the 1-bit look
+// of the framebuffer while smoothing diagonals, smooth2x blends colours
+// along the edges it finds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scaler {
+    #[default]
+    Nearest,
+    // Also known as EPX / AdvMAME2x
+    Scale2x,
+    Scale3x,
+    Scale4x,
+    Eagle,
+    Smooth2x,
+}
+
+impl Scaler {
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        [
+            "nearest", "scale2x", "scale3x", "scale4x", "eagle", "smooth2x",
+        ]
+        .into_iter()
+    }
+
+    pub fn parse(name: &str) -> Result<Scaler, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "nearest" | "none" => Ok(Scaler::Nearest),
+            "scale2x" | "epx" | "advmame2x" => Ok(Scaler::Scale2x),
+            "scale3x" | "advmame3x" => Ok(Scaler::Scale3x),
+            "scale4x" => Ok(Scaler::Scale4x),
+            "eagle" => Ok(Scaler::Eagle),
+            "smooth2x" => Ok(Scaler::Smooth2x),
+            _ => Err(format!(
+                "unknown scaler '{}', expected one of: {}",
+                name,
+                Scaler::names().collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scaler::Nearest => "nearest",
+            Scaler::Scale2x => "scale2x",
+            Scaler::Scale3x => "scale3x",
+            Scaler::Scale4x => "scale4x",
+            Scaler::Eagle => "eagle",
+            Scaler::Smooth2x => "smooth2x",
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        match self {
+            Scaler::Nearest => 1,
+            Scaler::Scale2x | Scaler::Eagle | Scaler::Smooth2x => 2,
+            Scaler::Scale3x => 3,
+            Scaler::Scale4x => 4,
+        }
+    }
+
+    pub fn apply(&self, image: &Image) -> Image {
+        match self {
+            Scaler::Nearest => image.clone(),
+            Scaler::Scale2x => scale2x(image),
+            Scaler::Scale3x => scale3x(image),
+            Scaler::Scale4x => scale2x(&scale2x(image)),
+            Scaler::Eagle => eagle(image),
+            Scaler::Smooth2x => smooth2x(image),
+        }
+    }
+}
+
+// The 3x3 neighbourhood of a pixel, borders are clamped:
+// A B C
+// D E F
+// G H I
+#[derive(Clone, Copy)]
+struct Neighbours {
+    a: Rgb,
+    b: Rgb,
+    c: Rgb,
+    d: Rgb,
+    e: Rgb,
+    f: Rgb,
+    g: Rgb,
+    h: Rgb,
+    i: Rgb,
+}
+
+fn neighbours(image: &Image, x: usize, y: usize) -> Neighbours {
+    let left = x.saturating_sub(1);
+    let right = (x + 1).min(image.width - 1);
+    let up = y.saturating_sub(1);
+    let down = (y + 1).min(image.height - 1);
+
+    Neighbours {
+        a: image.get(left, up),
+        b: image.get(x, up),
+        c: image.get(right, up),
+        d: image.get(left, y),
+        e: image.get(x, y),
+        f: image.get(right, y),
+        g: image.get(left, down),
+        h: image.get(x, down),
+        i: image.get(right, down),
+    }
+}
+
+// Calls `block` for every source pixel, which returns the factor x factor
+// output block in row major order
+fn expand<F>(image: &Image, factor: usize, block: F) -> Image
+where
+    F: Fn(&Neighbours) -> Vec<Rgb>,
+{
+    let mut out = Image::new(image.width * factor, image.height * factor, Rgb::BLACK);
+
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let pixels = block(&neighbours(image, x, y));
+            for (i, color) in pixels.into_iter().enumerate() {
+                out.set(x * factor + i % factor, y * factor + i / factor, color);
+            }
+        }
+    }
+
+    out
+}
+
+fn scale2x(image: &Image) -> Image {
+    expand(image, 2, |n| {
+        let Neighbours { b, d, e, f, h, .. } = *n;
+
+        if b != h && d != f {
+            vec![
+                if d == b { d } else { e },
+                if b == f { f } else { e },
+                if d == h { d } else { e },
+                if h == f { f } else { e },
+            ]
+        } else {
+            vec![e; 4]
+        }
+    })
+}
+
+fn scale3x(image: &Image) -> Image {
+    expand(image, 3, |n| {
+        let Neighbours {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            g,
+            h,
+            i,
+        } = *n;
+
+        if b != h && d != f {
+            vec![
+                if d == b { d } else { e },
+                if (d == b && e != c) || (b == f && e != a) {
+                    b
+                } else {
+                    e
+                },
+                if b == f { f } else { e },
+                if (d == b && e != g) || (d == h && e != a) {
+                    d
+                } else {
+                    e
+                },
+                e,
+                if (b == f && e != i) || (h == f && e != c) {
+                    f
+                } else {
+                    e
+                },
+                if d == h { d } else { e },
+                if (d == h && e != i) || (h == f && e != g) {
+                    h
+                } else {
+                    e
+                },
+                if h == f { f } else { e },
+            ]
+        } else {
+            vec![e; 9]
+        }
+    })
+}
+
+fn eagle(image: &Image) -> Image {
+    expand(image, 2, |n| {
+        let Neighbours {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            g,
+            h,
+            i,
+        } = *n;
+
+        vec![
+            if d == a && a == b { a } else { e },
+            if b == c && c == f { c } else { e },
+            if d == g && g == h { g } else { e },
+            if f == i && i == h { i } else { e },
+        ]
+    })
+}
+
+// Colours are told apart by these differences in YUV, the thresholds of
+// hq2x, closer colours are treated as the same one
+const Y_THRESHOLD: f32 = 48.0;
+const U_THRESHOLD: f32 = 7.0;
+const V_THRESHOLD: f32 = 6.0;
+
+fn yuv(color: Rgb) -> [f32; 3] {
+    let (r, g, b) = (color.0 as f32, color.1 as f32, color.2 as f32);
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        -0.169 * r - 0.331 * g + 0.5 * b,
+        0.5 * r - 0.419 * g - 0.081 * b,
+    ]
+}
+
+fn differ(x: Rgb, y: Rgb) -> bool {
+    let ([y1, u1, v1], [y2, u2, v2]) = (yuv(x), yuv(y));
+    (y1 - y2).abs() > Y_THRESHOLD || (u1 - u2).abs() > U_THRESHOLD || (v1 - v2).abs() > V_THRESHOLD
+}
+
+// Weighted average, truncated to whole channel values
+fn blend(colors: &[(Rgb, u32)]) -> Rgb {
+    let total: u32 = colors.iter().map(|(_, weight)| weight).sum();
+    let channel = |get: fn(Rgb) -> u8| {
+        let sum: u32 = colors.iter().map(|&(c, w)| get(c) as u32 * w).sum();
+        (sum / total) as u8
+    };
+    Rgb(channel(|c| c.0), channel(|c| c.1), channel(|c| c.2))
+}
+
+// The neighbours of the centre `c` that decide one output pixel of smooth2x:
+// `a` above or below it, `b` beside it and `d` diagonal to it, all on the
+// side of the corner. `e` continues the row of `a` on the far side and `f`
+// the column of `b`, telling lines that run on from lone corners.
+struct Corner {
+    c: Rgb,
+    a: Rgb,
+    b: Rgb,
+    d: Rgb,
+    e: Rgb,
+    f: Rgb,
+}
+
+// Lines leaving a corner at a shallow or steep angle also blend into the
+// output pixel next to it along the row or the column
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Slope {
+    None,
+    Shallow,
+    Steep,
+}
+
+impl Corner {
+    // Whether `a` and `b` both differ from the centre, the corner lying on
+    // an edge
+    fn on_edge(&self) -> bool {
+        differ(self.c, self.a) && differ(self.c, self.b)
+    }
+
+    // Blended strongly with both sides, when a line coming from the corner
+    // next to it runs through this one
+    fn slope(&self) -> Rgb {
+        blend(&[(self.c, 2), (self.a, 3), (self.b, 3)])
+    }
+
+    fn pixel(&self) -> (Rgb, Slope) {
+        let Corner { c, a, b, d, e, f } = *self;
+        let diagonal = differ(c, d);
+
+        let pixel = match (differ(c, a), differ(c, b)) {
+            (false, false) => blend(&[(c, 2), (a, 1), (b, 1)]),
+            (true, false) if diagonal => blend(&[(c, 3), (b, 1)]),
+            (true, false) => blend(&[(c, 2), (d, 1), (b, 1)]),
+            (false, true) if diagonal => blend(&[(c, 3), (a, 1)]),
+            (false, true) => blend(&[(c, 2), (d, 1), (a, 1)]),
+            // Two different colours meet at the corner, no edge to follow
+            (true, true) if differ(a, b) && diagonal => c,
+            (true, true) if differ(a, b) => blend(&[(c, 3), (d, 1)]),
+            (true, true) => match (differ(c, e), differ(c, f)) {
+                (true, true) if diagonal => blend(&[(c, 14), (a, 1), (b, 1)]),
+                (true, true) => blend(&[(c, 6), (a, 1), (b, 1)]),
+                (true, false) => return (blend(&[(c, 5), (a, 2), (b, 1)]), Slope::Shallow),
+                (false, true) => return (blend(&[(c, 5), (b, 2), (a, 1)]), Slope::Steep),
+                (false, false) => blend(&[(c, 2), (a, 1), (b, 1)]),
+            },
+        };
+        (pixel, Slope::None)
+    }
+}
+
+// Blends each output pixel from its corner of the 3x3 neighbourhood, after
+// the ideas of Maxim Stepin's hq2x. It is not hq2x itself, which looks the
+// whole neighbourhood up in a table of 256 cases, so its output differs.
+fn smooth2x(image: &Image) -> Image {
+    expand(image, 2, |n| {
+        let Neighbours {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            g,
+            h,
+            i,
+        } = *n;
+
+        // Top left, top right, bottom left and bottom right
+        let corners = [
+            Corner {
+                c: e,
+                a: b,
+                b: d,
+                d: a,
+                e: c,
+                f: g,
+            },
+            Corner {
+                c: e,
+                a: b,
+                b: f,
+                d: c,
+                e: a,
+                f: i,
+            },
+            Corner {
+                c: e,
+                a: h,
+                b: d,
+                d: g,
+                e: i,
+                f: a,
+            },
+            Corner {
+                c: e,
+                a: h,
+                b: f,
+                d: i,
+                e: g,
+                f: c,
+            },
+        ];
+        let mut pixels: Vec<Rgb> = Vec::with_capacity(4);
+        let mut slopes = Vec::with_capacity(4);
+        for corner in &corners {
+            let (pixel, slope) = corner.pixel();
+            pixels.push(pixel);
+            slopes.push(slope);
+        }
+
+        // Corners next to each other share a row (index ^ 1) or a column
+        // (index ^ 2)
+        for (index, slope) in slopes.into_iter().enumerate() {
+            let next = match slope {
+                Slope::None => continue,
+                Slope::Shallow => index ^ 1,
+                Slope::Steep => index ^ 2,
+            };
+            if !corners[next].on_edge() {
+                pixels[next] = corners[next].slope();
+            }
+        }
+        pixels
+    })
+}
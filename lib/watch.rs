@@ -0,0 +1,61 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+// How often the file is looked at
+const INTERVAL: Duration = Duration::from_millis(250);
+
+// Modification time and size
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+// Notices when a file is rewritten, such as a ROM being rebuilt
+#[derive(Debug, Clone)]
+pub struct FileWatcher {
+    path: PathBuf,
+    seen: Stamp,
+    last: Stamp,
+    checked: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> Self {
+        let seen = stamp(path);
+        FileWatcher {
+            path: path.to_path_buf(),
+            seen,
+            last: seen,
+            checked: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Whether the file changed since the last time this returned true. A
+    // change is only reported once the file stays the same for a check, so
+    // that a build still writing it is not picked up halfway.
+    pub fn changed(&mut self) -> bool {
+        if self.checked.elapsed() < INTERVAL {
+            return false;
+        }
+        self.checked = Instant::now();
+
+        let current = stamp(&self.path);
+        let settled = current == self.last;
+        self.last = current;
+
+        if settled && current.is_some() && current != self.seen {
+            self.seen = current;
+            return true;
+        }
+        false
+    }
+}
@@ -0,0 +1,418 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use lib::assembler;
+use lib::chip::Chip;
+use lib::chip::quirks;
+use lib::config::Layer;
+use lib::database;
+use lib::disassembler;
+use lib::frontend::NUM_KEYS;
+use lib::frontend::headless::{HeadlessRenderer, NullAudio};
+use lib::frontend::movie::MoviePlayer;
+use lib::render::crt;
+
+use crate::{Settings, bad_argument, describe, fail, load, parse_number, usage, value};
+
+// The ROM of a command that takes nothing but settings besides it, loaded
+// with them and the config file
+fn single_rom(args: &[String]) -> Chip {
+    let mut settings = Settings::default();
+    let mut rom = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if settings.parse_flag(arg, &mut iter) {
+            continue;
+        }
+        match arg.as_str() {
+            _ if arg.starts_with("--") => bad_argument(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => usage(),
+        }
+    }
+    let rom = rom.unwrap_or_else(|| usage());
+    settings.load_config();
+
+    load(rom, &settings).unwrap_or_else(|err| fail(err)).0
+}
+
+// Runs a ROM without any output for `frames` frames
+fn run_frames(chip: &mut Chip, input: &mut MoviePlayer, frames: u64) {
+    let mut renderer = HeadlessRenderer::default();
+    for _ in 0..frames {
+        if let Err(fault) = chip.step(&mut renderer, input, &mut NullAudio) {
+            fail(fault.to_string());
+        }
+    }
+}
+
+pub fn disasm(args: &[String]) {
+    let chip = single_rom(args);
+    print!("{}", disassembler::disassemble(chip.program()));
+}
+
+pub fn asm(args: &[String]) {
+    let mut source = None;
+    let mut out = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => out = Some(PathBuf::from(value(&mut iter))),
+            _ if arg.starts_with("--") => bad_argument(format!("unknown option '{}'", arg)),
+            _ if source.is_none() => source = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+
+    let source = source.unwrap_or_else(|| usage());
+    let text = fs::read_to_string(&source)
+        .unwrap_or_else(|err| fail(format!("Cannot read {}: {}", source.display(), err)));
+    let program = assembler::assemble(&text)
+        .unwrap_or_else(|err| fail(format!("{}: {}", source.display(), err)));
+
+    // Next to the source by default
+    let out = out.unwrap_or_else(|| source.with_extension("ch8"));
+    fs::write(&out, &program)
+        .unwrap_or_else(|err| fail(format!("Cannot write {}: {}", out.display(), err)));
+    eprintln!("{}: {} bytes", out.display(), program.len());
+}
+
+// Runs a ROM headless and checks its screen, or prints it so that it can be
+// used as the expected screen of later runs
+pub fn test(args: &[String]) {
+    let mut settings = Settings::default();
+    let mut rom = None;
+    let mut frames: u64 = 300;
+    let mut expect = None;
+    let mut input = MoviePlayer::default();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if settings.parse_flag(arg, &mut iter) {
+            continue;
+        }
+        match arg.as_str() {
+            "--frames" => frames = parse_number(iter.next()),
+            "--expect" => expect = Some(PathBuf::from(value(&mut iter))),
+            "--input-movie" => {
+                input = MoviePlayer::load(value(&mut iter).as_ref()).unwrap_or_else(|err| fail(err))
+            }
+            _ if arg.starts_with("--") => bad_argument(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let rom = rom.unwrap_or_else(|| usage());
+    settings.load_config();
+    let (mut chip, _) = load(rom, &settings).unwrap_or_else(|err| fail(err));
+    run_frames(&mut chip, &mut input, frames);
+    let screen = chip.framebuffer().to_text();
+
+    let Some(expect) = expect else {
+        print!("{}", screen);
+        return;
+    };
+    let expected = fs::read_to_string(&expect)
+        .unwrap_or_else(|err| fail(format!("Cannot read {}: {}", expect.display(), err)));
+
+    let rows = |text: &str| -> Vec<String> { text.lines().map(|l| l.trim().to_string()).collect() };
+    let (actual, expected) = (rows(&screen), rows(&expected));
+    if actual == expected {
+        println!("{}: ok after {} frames", rom, frames);
+        return;
+    }
+
+    eprintln!(
+        "{}: the screen after {} frames differs from {}",
+        rom,
+        frames,
+        expect.display()
+    );
+    for (row, line) in actual.iter().enumerate() {
+        let marker = if expected.get(row) == Some(line) {
+            ' '
+        } else {
+            '>'
+        };
+        eprintln!("{} {}", marker, line);
+    }
+    std::process::exit(1);
+}
+
+// Emulates as fast as possible and reports the speed
+pub fn bench(args: &[String]) {
+    let mut settings = Settings::default();
+    let mut rom = None;
+    let mut frames: u64 = 3600;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if settings.parse_flag(arg, &mut iter) {
+            continue;
+        }
+        match arg.as_str() {
+            "--frames" => frames = parse_number(iter.next()),
+            _ if arg.starts_with("--") => bad_argument(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    let rom = rom.unwrap_or_else(|| usage());
+    settings.load_config();
+    let (mut chip, _) = load(rom, &settings).unwrap_or_else(|err| fail(err));
+    let start = Instant::now();
+    run_frames(&mut chip, &mut MoviePlayer::default(), frames);
+    let seconds = start.elapsed().as_secs_f64().max(f64::EPSILON);
+
+    let per_second = frames as f64 / seconds;
+    println!(
+        "{} frames in {:.3} s: {:.0} frames/s, {:.1}x real time, up to {:.0} instructions/s",
+        frames,
+        seconds,
+        per_second,
+        per_second / 60.0,
+        per_second * chip.tickrate() as f64
+    );
+}
+
+pub fn info(args: &[String]) {
+    let chip = single_rom(args);
+    let program = chip.program();
+
+    println!("Size: {} bytes", program.len());
+    println!("SHA-1: {}", database::sha1(program));
+    match chip.rom_info() {
+        Some(info) => {
+            describe(info);
+            if let Some(platform) = &info.platform {
+                println!("Platform: {}", platform);
+            }
+        }
+        None => println!("Not in the ROM database"),
+    }
+    println!("Quirks: {}", chip.quirks().to_spec());
+    println!("Instructions per frame: {}", chip.tickrate());
+}
+
+// Which quirks matter for a ROM, from the instructions it contains
+pub fn compat(args: &[String]) {
+    let chip = single_rom(args);
+    let program = chip.program();
+
+    let unknown = program
+        .chunks_exact(2)
+        .filter(|word| disassembler::mnemonic(u16::from_be_bytes([word[0], word[1]])).is_none())
+        .count();
+
+    match chip.rom_info().and_then(|info| info.platform.as_ref()) {
+        Some(platform) => println!("Database platform: {} (--platform {})", platform, platform),
+        None => println!("Database platform: unknown"),
+    }
+    println!("Quirks in use: {}", chip.quirks().to_spec());
+    println!("Instructions affected by each quirk:");
+    for (quirk, count) in quirks::affected_instructions(program) {
+        println!("  {:<12}{}", quirk, count);
+    }
+    if unknown > 0 {
+        println!(
+            "{} words are not CHIP-8 instructions, they are data or need another platform",
+            unknown
+        );
+    }
+}
+
+// Where the effective value of a setting comes from
+fn origin(layers: &[Layer], key: &str, rom_default: Option<&str>) -> String {
+    layers
+        .iter()
+        .rev()
+        .find(|layer| layer.sets(key))
+        .map(|layer| layer.source.clone())
+        .or(rom_default.map(str::to_string))
+        .unwrap_or_else(|| "default".to_string())
+}
+
+fn quoted(values: &[String]) -> String {
+    let values: Vec<String> = values.iter().map(|v| format!("{:?}", v)).collect();
+    match values.as_slice() {
+        [value] => value.clone(),
+        _ => format!("[{}]", values.join(", ")),
+    }
+}
+
+// Prints the settings a ROM runs with as a config file, with where each
+// one comes from
+pub fn config(args: &[String]) {
+    let Some(("dump", args)) = args.split_first().map(|(c, rest)| (c.as_str(), rest)) else {
+        usage();
+    };
+
+    let mut settings = Settings::default();
+    let mut rom = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if settings.parse_flag(arg, &mut iter) {
+            continue;
+        }
+        match arg.as_str() {
+            _ if arg.starts_with("--") => bad_argument(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => usage(),
+        }
+    }
+    settings.load_config();
+
+    let (chip, layer, layers) = match rom {
+        Some(rom) => {
+            let (chip, layer) = load(rom, &settings).unwrap_or_else(|err| fail(err));
+            let layers = settings.layers(Some(rom), Some(chip.program()));
+            (Some(chip), layer, layers)
+        }
+        None => {
+            let layers = settings.layers(None, None);
+            (None, Layer::merged(&layers), layers)
+        }
+    };
+    let options = settings.window_options(&layer);
+    // Settings a ROM can bring along from the database or its cartridge
+    let from_rom = rom.map(|rom| {
+        if rom.to_ascii_lowercase().ends_with(".gif") {
+            "cartridge"
+        } else {
+            "rom database"
+        }
+    });
+    let known = chip.as_ref().is_some_and(|chip| chip.rom_info().is_some());
+    let rom_default = known.then_some(from_rom).flatten();
+
+    if let Some(rom) = rom {
+        println!("# Settings for {}", rom);
+    }
+    let by_platform = layer.quirks.is_none();
+    let line = |key: &str, value: String, rom_default: Option<&str>| {
+        // Quirks picked with a platform come from where the platform is set
+        let source = if key == "quirks" && by_platform {
+            "platform"
+        } else {
+            key
+        };
+        // Keys and macros go under their table header
+        let name = match key.split_once('.') {
+            Some(("keys" | "autofire" | "macros", name)) => format!("{:?}", name),
+            Some(("crt", name)) => name.to_string(),
+            _ => key.to_string(),
+        };
+        println!(
+            "{:<39} # {}",
+            format!("{} = {}", name, value),
+            origin(&layers, source, rom_default)
+        );
+    };
+
+    if let Some(platform) = &layer.platform {
+        line("platform", format!("{:?}", platform), None);
+    }
+    if let Some(chip) = &chip {
+        line(
+            "quirks",
+            format!("{:?}", chip.quirks().to_spec()),
+            rom_default,
+        );
+        line("ipf", chip.tickrate().to_string(), rom_default);
+        let playback = chip.playback();
+        line("fast-forward", playback.fast_forward_rate.to_string(), None);
+        line("slow-motion", playback.slow_motion_rate.to_string(), None);
+    }
+    if let Some(seed) = layer.seed {
+        line("seed", seed.to_string(), None);
+    }
+    let palette_default = chip
+        .as_ref()
+        .is_some_and(|_| layer.palette.is_some())
+        .then_some(from_rom)
+        .flatten();
+    line(
+        "palette",
+        format!("{:?}", options.palette.to_spec()),
+        palette_default,
+    );
+    line("scale", options.scale.to_string(), None);
+    line("fullscreen", options.fullscreen.to_string(), None);
+    line("integer-scaling", options.integer_scaling.to_string(), None);
+    let persistence = options
+        .persistence
+        .map_or("none".to_string(), |mode| mode.to_spec());
+    line("persistence", format!("{:?}", persistence), None);
+    line("scaler", format!("{:?}", options.scaler.name()), None);
+    // The effects go in a [crt] table when they are on
+    if options.crt.is_none() {
+        line("crt", "false".to_string(), None);
+    }
+    line("osd", options.osd.to_string(), None);
+    line("screenshot-osd", options.screenshot_osd.to_string(), None);
+    line("keypad", options.keypad.to_string(), None);
+    line(
+        "autofire-rate",
+        options.macros.autofire_rate.to_string(),
+        None,
+    );
+    line("volume", options.volume.to_string(), None);
+    line("tone", options.tone_hz.to_string(), None);
+    line("mute", (layer.mute == Some(true)).to_string(), None);
+    let terminal = options.terminal.map_or("none", |protocol| protocol.name());
+    line("terminal", format!("{:?}", terminal), None);
+    line("terminal-scale", options.terminal_scale.to_string(), None);
+
+    println!();
+    println!("[keys]");
+    for key in 0..NUM_KEYS as u8 {
+        line(
+            &format!("keys.{:X}", key),
+            quoted(options.keymap.bindings(key)),
+            None,
+        );
+    }
+
+    if let Some(settings) = &options.crt {
+        println!();
+        println!("[crt]");
+        for (name, _) in crt::EFFECTS {
+            line(
+                &format!("crt.{}", name),
+                settings.effect(name).unwrap_or_default().to_string(),
+                None,
+            );
+        }
+    }
+
+    let autofire: Vec<u8> = (0..NUM_KEYS as u8)
+        .filter(|&key| !options.macros.autofire(key).is_empty())
+        .collect();
+    if !autofire.is_empty() {
+        println!();
+        println!("[autofire]");
+        for key in autofire {
+            line(
+                &format!("autofire.{:X}", key),
+                quoted(options.macros.autofire(key)),
+                None,
+            );
+        }
+    }
+
+    if !options.macros.macros().is_empty() {
+        println!();
+        println!("[macros]");
+        for (name, steps) in options.macros.macros() {
+            line(
+                &format!("macros.{}", name),
+                format!("{:?}", steps.steps()),
+                None,
+            );
+        }
+    }
+}
@@ -1,16 +1,653 @@
-use lib::chip;
+mod commands;
+
+use lib::capture;
+use lib::capture::recording::{RecordFormat, Recorder};
+use lib::capture::video::{VideoFormat, VideoWriter};
+use lib::cartridge::{self, CartOptions, Cartridge};
+use lib::chip::quirks::Quirks;
+use lib::chip::{self, Fault, RunOutcome};
+use lib::config::{self, Config, Layer};
+use lib::database::{self, RomInfo};
+use lib::frontend::headless::{HeadlessRenderer, NullAudio};
+use lib::frontend::movie::{MoviePlayer, MovieRecorder};
+use lib::frontend::recording::RecordingRenderer;
+use lib::frontend::sdl::{SdlAudio, SdlFrontend, SdlOptions, SdlRenderer};
+use lib::frontend::terminal::{TerminalMode, TerminalRenderer};
+use lib::frontend::video::{VideoRenderer, WavAudio};
+use lib::frontend::{Action, InputSource, Renderer};
+use lib::graphics::{Protocol, TerminalGraphics};
+use lib::launcher::Launcher;
+use lib::palette::Palette;
+use lib::render::Pipeline;
+use lib::render::scaler::Scaler;
 use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use toml::Value;
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
+fn usage() -> ! {
+    eprintln!("Usage: rust_chip8 [run] [options] [rom|cart.gif]");
+    eprintln!("       rust_chip8 export-cart [options] <rom> <out.gif>");
+    eprintln!("       rust_chip8 disasm [emulation options] <rom>");
+    eprintln!("       rust_chip8 asm <source.8o> [-o <out.ch8>]");
+    eprintln!(
+        "       rust_chip8 test [--frames <n>] [--expect <screen.txt>] [emulation options] <rom>"
+    );
+    eprintln!("       rust_chip8 bench [--frames <n>] [emulation options] <rom>");
+    eprintln!("       rust_chip8 info [emulation options] <rom>");
+    eprintln!("       rust_chip8 compat [emulation options] <rom>");
+    eprintln!("       rust_chip8 config dump [options] [rom]");
+    eprintln!();
+    eprintln!(
+        "Emulation options: --platform <name> --quirks <list> --ipf <n> --seed <n> --config <file>"
+    );
+    eprintln!(
+        "Window options: --scale <n> --palette <name|#bg,#fg[,#fg2,#blend]> --fullscreen --persistence <fade:N|blend:N> --scaler <name> --crt --osd --screenshot-osd --keypad --autofire-rate <frames> --fast-forward <rate> --slow-motion <rate> --volume <0-1> --tone <hz> --mute --library <dir> --watch --record-input <movie>"
+    );
+    eprintln!("Terminal options: --terminal <sixel|kitty> --terminal-scale <n>");
+    eprintln!(
+        "Headless options: --headless --frames <n> --input-movie <movie> --record <out.gif|out.png> --screenshot-at-frame <n> <out.png> --video <out.y4m|out.rgb|-> --video-format <y4m|raw> --wav <out.wav>"
+    );
+    eprintln!("Platforms: {}", database::PLATFORMS.join(", "));
+    eprintln!(
+        "Palettes: {}",
+        Palette::names().collect::<Vec<_>>().join(", ")
+    );
+    eprintln!(
+        "Scalers: {}",
+        Scaler::names().collect::<Vec<_>>().join(", ")
+    );
+    eprintln!("Quirks: {}", Quirks::names().collect::<Vec<_>>().join(", "));
+    std::process::exit(2);
+}
+
+// Reports a bad argument and exits like `usage`
+fn bad_argument(message: String) -> ! {
+    eprintln!("Error: {}", message);
+    usage()
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("Error: {}", message);
+    std::process::exit(1);
+}
+
+fn parse_number<T: std::str::FromStr>(arg: Option<&String>) -> T {
+    match arg {
+        Some(arg) => arg
+            .parse()
+            .unwrap_or_else(|_| bad_argument(format!("'{}' is not a valid number", arg))),
+        None => usage(),
+    }
+}
+
+fn value<'a>(iter: &mut impl Iterator<Item = &'a String>) -> &'a String {
+    iter.next().unwrap_or_else(|| usage())
+}
 
-    if args.len() != 2 {
-        std::process::exit(0);
+#[derive(Default)]
+struct Headless {
+    enabled: bool,
+    frames: Option<u64>,
+    input_movie: Option<PathBuf>,
+    record: Option<PathBuf>,
+    screenshot: Option<(u64, PathBuf)>,
+    video: Option<PathBuf>,
+    video_format: Option<VideoFormat>,
+    wav: Option<PathBuf>,
+}
+
+// The config file and the command line, layered for each ROM
+#[derive(Default)]
+struct Settings {
+    config: Config,
+    cli: Layer,
+    config_path: Option<PathBuf>,
+}
+
+impl Settings {
+    // Takes --config and the flags named after a config setting
+    fn parse_flag<'a>(&mut self, arg: &str, iter: &mut impl Iterator<Item = &'a String>) -> bool {
+        if arg == "--config" {
+            self.config_path = Some(PathBuf::from(value(iter)));
+            return true;
+        }
+        let Some(key) = arg
+            .strip_prefix("--")
+            .filter(|key| config::KEYS.contains(key) || *key == "tickrate")
+        else {
+            return false;
+        };
+
+        let setting = match key {
+            "fullscreen" | "integer-scaling" | "crt" | "osd" | "screenshot-osd" | "keypad"
+            | "mute" => Value::Boolean(true),
+            _ => {
+                let raw = value(iter);
+                raw.parse()
+                    .map(Value::Integer)
+                    .or_else(|_| raw.parse().map(Value::Float))
+                    .unwrap_or_else(|_| Value::String(raw.clone()))
+            }
+        };
+        if self.cli.source.is_empty() {
+            self.cli.source = "command line".to_string();
+        }
+        self.cli
+            .set(key, &setting)
+            .unwrap_or_else(|err| bad_argument(format!("--{}: {}", key, err)));
+        true
+    }
+
+    // Reads the config file once the flags are parsed
+    fn load_config(&mut self) {
+        let config = match &self.config_path {
+            Some(path) => Config::load(path),
+            None => Config::load_default(),
+        };
+        self.config = config.unwrap_or_else(|err| fail(err.to_string()));
+    }
+
+    // From the least to the most important
+    fn layers(&self, rom: Option<&str>, program: Option<&[u8]>) -> Vec<Layer> {
+        let mut layers = self.config.layers(rom.map(Path::new), program);
+        layers.push(self.cli.clone());
+        layers
     }
 
-    println!("{}", args[1]);
+    fn window_options(&self, layer: &Layer) -> SdlOptions {
+        let mut options = SdlOptions::default();
+        layer.apply_window(&mut options);
+        options
+    }
+}
 
+// Loads a ROM with the settings that apply to it. The palette of the merged
+// settings falls back to the one the cartridge or the database ask for.
+fn load(rom: &str, settings: &Settings) -> Result<(chip::Chip, Layer), String> {
     let mut a = chip::Chip::new();
-    a.load_program(&args[1]);
-    a.interpret();
+    a.load_program(rom)
+        .map_err(|err| format!("Cannot load {}: {}", rom, err))?;
+    let palette = a.rom_info().and_then(|info| info.palette.clone());
+
+    let mut layer = Layer::merged(&settings.layers(Some(rom), Some(a.program())));
+    layer.apply(&mut a);
+    if layer.palette.is_none() {
+        layer.palette = palette;
+    }
+    Ok((a, layer))
+}
+
+fn describe(info: &RomInfo) {
+    match info.authors.as_slice() {
+        [] => println!("{}", info.title),
+        authors => println!("{} by {}", info.title, authors.join(", ")),
+    }
+    if !info.keys.is_empty() {
+        let keys: Vec<String> = info
+            .keys
+            .iter()
+            .map(|(action, key)| format!("{} {:X}", action, key))
+            .collect();
+        println!("Keys: {}", keys.join(", "));
+    }
+}
+
+// Same look as the window, at `scale` image pixels per CHIP-8 pixel
+fn capture_pipeline(options: &SdlOptions) -> Pipeline {
+    let mut pipeline = Pipeline::new(options.palette.clone(), options.persistence);
+    pipeline.scaler = options.scaler;
+    pipeline.scale = (options.scale / options.scaler.factor()).max(1);
+    pipeline.crt = options.crt;
+    pipeline
+}
+
+// Everything a headless run needs apart from the renderer
+struct HeadlessRun<'a> {
+    chip: &'a mut chip::Chip,
+    input: MoviePlayer,
+    audio: Option<WavAudio<BufWriter<File>>>,
+    frames: u64,
+    screenshot: Option<(u64, PathBuf, Pipeline)>,
+    // Where the program stopped, the files written so far are still saved
+    fault: Option<Fault>,
+}
+
+impl HeadlessRun<'_> {
+    fn run<R: Renderer>(&mut self, renderer: &mut R) {
+        for frame in 1..=self.frames {
+            let result = match &mut self.audio {
+                Some(audio) => self.chip.step(renderer, &mut self.input, audio),
+                None => self.chip.step(renderer, &mut self.input, &mut NullAudio),
+            };
+            if let Err(fault) = result {
+                self.fault = Some(fault);
+                return;
+            }
+
+            if let Some((at, out, pipeline)) = &mut self.screenshot {
+                // The persistence filter has to see every frame
+                if pipeline.persistence.is_some() || frame == *at {
+                    let image = pipeline.render(self.chip.framebuffer());
+                    if frame == *at {
+                        capture::save_png(&image, out).unwrap_or_else(|err| {
+                            fail(format!("Cannot save {}: {}", out.display(), err))
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn run_with_video<R: Renderer>(
+        &mut self,
+        renderer: R,
+        video: Option<(VideoWriter<Box<dyn Write>>, Pipeline)>,
+    ) -> R {
+        match video {
+            Some((writer, pipeline)) => {
+                let mut renderer = VideoRenderer::new(renderer, pipeline, writer);
+                self.run(&mut renderer);
+                renderer
+                    .finish()
+                    .unwrap_or_else(|err| fail(format!("Cannot write the video: {}", err)))
+            }
+            None => {
+                let mut renderer = renderer;
+                self.run(&mut renderer);
+                renderer
+            }
+        }
+    }
+}
+
+fn create(path: &Path) -> BufWriter<File> {
+    BufWriter::new(
+        File::create(path)
+            .unwrap_or_else(|err| fail(format!("Cannot create {}: {}", path.display(), err))),
+    )
+}
+
+// A path of "-" writes the video to stdout
+fn video_writer(
+    options: &SdlOptions,
+    headless: &Headless,
+) -> Option<(VideoWriter<Box<dyn Write>>, Pipeline)> {
+    let path = headless.video.as_ref()?;
+    let to_stdout = path.as_os_str() == "-";
+    let format = headless.video_format.unwrap_or({
+        let y4m = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("y4m"));
+        if to_stdout || y4m {
+            VideoFormat::Y4m
+        } else {
+            VideoFormat::Raw
+        }
+    });
+
+    let pipeline = capture_pipeline(options);
+    if format == VideoFormat::Raw {
+        let (width, height) = pipeline.output_size();
+        eprintln!("Writing rgb24 frames of {}x{} at 60 fps", width, height);
+    }
+
+    let out: Box<dyn Write> = if to_stdout {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        Box::new(create(path))
+    };
+    Some((VideoWriter::new(out, format), pipeline))
+}
+
+// Runs as fast as possible without opening a window
+fn run_headless(chip: &mut chip::Chip, options: &SdlOptions, headless: Headless) {
+    let input = match &headless.input_movie {
+        Some(path) => MoviePlayer::load(path).unwrap_or_else(|err| fail(err)),
+        None => MoviePlayer::default(),
+    };
+
+    let frames = headless
+        .frames
+        .or(headless.screenshot.as_ref().map(|(at, _)| *at))
+        .or((!input.is_empty()).then_some(input.len() as u64))
+        .unwrap_or_else(|| {
+            fail("--headless needs --frames, --screenshot-at-frame or --input-movie".to_string())
+        });
+
+    let audio = headless.wav.as_ref().map(|path| {
+        WavAudio::new(create(path), options.tone_hz, options.volume)
+            .unwrap_or_else(|err| fail(format!("Cannot write {}: {}", path.display(), err)))
+    });
+
+    let video = video_writer(options, &headless);
+
+    let mut run = HeadlessRun {
+        chip,
+        input,
+        audio,
+        frames,
+        screenshot: headless
+            .screenshot
+            .map(|(at, out)| (at, out, capture_pipeline(options))),
+        fault: None,
+    };
+
+    match headless.record {
+        Some(path) => {
+            let format = RecordFormat::from_path(&path).unwrap_or_else(|| {
+                fail(format!(
+                    "Cannot tell the recording format of {}, use .gif or .png",
+                    path.display()
+                ))
+            });
+            let renderer = RecordingRenderer::new(
+                HeadlessRenderer::default(),
+                capture_pipeline(options),
+                Recorder::new(&path, format),
+            );
+
+            run.run_with_video(renderer, video)
+                .finish()
+                .unwrap_or_else(|err| fail(format!("Cannot save {}: {}", path.display(), err)));
+        }
+        None => {
+            run.run_with_video(HeadlessRenderer::default(), video);
+        }
+    }
+
+    if let (Some(audio), Some(path)) = (run.audio, &headless.wav) {
+        audio
+            .finish()
+            .unwrap_or_else(|err| fail(format!("Cannot write {}: {}", path.display(), err)));
+    }
+    if let Some(fault) = run.fault {
+        fail(fault.to_string());
+    }
+}
+
+// Draws the game in a terminal that shows Sixel or Kitty graphics, in the
+// palette colours. Terminals do not report key releases, so the keys, the
+// sound and quitting still go through the window, which stays blank.
+fn run_terminal(chip: &mut chip::Chip, options: SdlOptions, protocol: Protocol) {
+    let mut renderer = TerminalRenderer::new(TerminalMode::Graphics(TerminalGraphics {
+        protocol,
+        scale: options.terminal_scale,
+        foreground: options.palette.foreground(),
+        background: options.palette.background(),
+    }));
+    let mut frontend = SdlFrontend::new(options);
+
+    // Every frame is drawn from the top left corner of a clear screen
+    print!("\x1b[2J");
+    chip.interpret(&mut renderer, &mut frontend.input, &mut frontend.audio);
+}
+
+// Shows the menu until a ROM is picked or dropped on the window, None when
+// the user quits instead
+fn pick_rom(launcher: &mut Launcher, frontend: &mut SdlFrontend) -> Option<PathBuf> {
+    frontend.renderer.set_rom_name(None);
+    loop {
+        frontend.input.poll();
+        for action in frontend.input.actions() {
+            match action {
+                Action::Quit => return None,
+                Action::Open(path) => return Some(path),
+                _ => {}
+            }
+            if let Some(path) = launcher.handle(action) {
+                return Some(path);
+            }
+        }
+
+        frontend.renderer.show_image(&launcher.render());
+        thread::sleep(chip::FRAME_DURATION);
+    }
+}
+
+// Plays a game until the user quits, goes back to the menu or opens a ROM
+// that loads, which is left in `next`
+fn play<I: InputSource>(
+    a: &mut chip::Chip,
+    renderer: &mut SdlRenderer,
+    input: &mut I,
+    audio: &mut SdlAudio,
+    settings: &Settings,
+    next: &mut Option<(PathBuf, (chip::Chip, Layer))>,
+) -> RunOutcome {
+    loop {
+        let outcome = a.interpret(renderer, input, audio);
+        let RunOutcome::Open(dropped) = &outcome else {
+            return outcome;
+        };
+        // A dropped file that does not load leaves the game running
+        match load(&dropped.to_string_lossy(), settings) {
+            Ok(loaded) => {
+                *next = Some((dropped.clone(), loaded));
+                return outcome;
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                renderer.show_message(&err);
+            }
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // Without a command the arguments are for `run`
+    let (command, args) = match args.first().map(String::as_str) {
+        Some(
+            command @ ("run" | "export-cart" | "disasm" | "asm" | "test" | "bench" | "info"
+            | "compat" | "config"),
+        ) => (command, &args[1..]),
+        Some("--help" | "-h" | "help") => usage(),
+        _ => ("run", &args[..]),
+    };
+
+    match command {
+        "disasm" => commands::disasm(args),
+        "asm" => commands::asm(args),
+        "test" => commands::test(args),
+        "bench" => commands::bench(args),
+        "info" => commands::info(args),
+        "compat" => commands::compat(args),
+        "config" => commands::config(args),
+        _ => run(args, command == "export-cart"),
+    }
+}
+
+fn run(args: &[String], export: bool) {
+    let mut settings = Settings::default();
+    let mut rom: Option<&String> = None;
+    let mut headless = Headless::default();
+    let mut record_input: Option<PathBuf> = None;
+    let mut library = PathBuf::from("roms");
+    let mut watch = false;
+
+    let mut export_out: Option<&String> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if settings.parse_flag(arg, &mut iter) {
+            continue;
+        }
+        match arg.as_str() {
+            "--record-input" => {
+                record_input = Some(PathBuf::from(iter.next().unwrap_or_else(|| usage())));
+            }
+            "--library" => {
+                library = PathBuf::from(iter.next().unwrap_or_else(|| usage()));
+            }
+            "--watch" => watch = true,
+            "--headless" => headless.enabled = true,
+            "--frames" => headless.frames = Some(parse_number(iter.next())),
+            "--input-movie" => {
+                headless.input_movie = Some(PathBuf::from(iter.next().unwrap_or_else(|| usage())));
+            }
+            "--record" => {
+                headless.record = Some(PathBuf::from(iter.next().unwrap_or_else(|| usage())));
+                headless.enabled = true;
+            }
+            "--video" => {
+                headless.video = Some(PathBuf::from(iter.next().unwrap_or_else(|| usage())));
+                headless.enabled = true;
+            }
+            "--video-format" => {
+                let name = iter.next().unwrap_or_else(|| usage());
+                headless.video_format = Some(VideoFormat::parse(name).unwrap_or_else(|err| {
+                    eprintln!("Error: {}", err);
+                    usage()
+                }));
+            }
+            "--wav" => {
+                headless.wav = Some(PathBuf::from(iter.next().unwrap_or_else(|| usage())));
+                headless.enabled = true;
+            }
+            "--screenshot-at-frame" => {
+                let frame = parse_number(iter.next());
+                let out = iter.next().unwrap_or_else(|| usage());
+                headless.screenshot = Some((frame, PathBuf::from(out)));
+                headless.enabled = true;
+            }
+            _ if arg.starts_with("--") => bad_argument(format!("unknown option '{}'", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ if export && export_out.is_none() => export_out = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    match (&headless.screenshot, headless.frames) {
+        (Some((0, _)), _) => bad_argument("frames are counted from 1".to_string()),
+        (Some((at, _)), Some(frames)) if *at > frames => bad_argument(format!(
+            "--screenshot-at-frame {} is after the last of the {} frames",
+            at, frames
+        )),
+        _ => {}
+    }
+
+    // Only the window has a menu to pick a ROM from
+    if rom.is_none() && (export || headless.enabled || record_input.is_some()) {
+        usage();
+    }
+
+    settings.load_config();
+
+    if export || headless.enabled {
+        let rom = rom.unwrap();
+        let (mut a, layer) = load(rom, &settings).unwrap_or_else(|err| fail(err));
+        let options = settings.window_options(&layer);
+
+        if export {
+            let out = export_out.unwrap_or_else(|| usage());
+            let cartridge = Cartridge {
+                program: a.program().to_vec(),
+                options: CartOptions {
+                    tickrate: a.tickrate(),
+                    quirks: a.quirks(),
+                    palette: Some(options.palette),
+                },
+            };
+            cartridge::save(&cartridge, Path::new(out)).unwrap_or_else(|err| fail(err));
+        } else {
+            // Nothing goes to stdout here, it may be carrying the video
+            run_headless(&mut a, &options, headless);
+        }
+        return;
+    }
+
+    // Window settings of a ROM section only apply to the ROM the window is
+    // opened with
+    let base = Layer::merged(&settings.layers(None, None));
+    let mut next = rom.map(|rom| {
+        let loaded = load(rom, &settings).unwrap_or_else(|err| fail(err));
+        (PathBuf::from(rom), loaded)
+    });
+    let options = settings.window_options(next.as_ref().map_or(&base, |(_, (_, layer))| layer));
+    if let Some(protocol) = options.terminal {
+        let Some((_, (mut a, _))) = next else {
+            bad_argument("the terminal has no menu, give a ROM to play".to_string());
+        };
+        run_terminal(&mut a, options, protocol);
+        return;
+    }
+    let default_palette = base.palette.unwrap_or_default();
+    let mut launcher = Launcher::new(&library, default_palette.clone());
+    let mut frontend = SdlFrontend::new(options);
+
+    loop {
+        let (path, (mut a, layer)) = match next.take() {
+            Some(loaded) => loaded,
+            None => {
+                // A ROM picked from the menu that fails to load sends us back there
+                let Some(path) = pick_rom(&mut launcher, &mut frontend) else {
+                    break;
+                };
+                match load(&path.to_string_lossy(), &settings) {
+                    Ok(loaded) => (path, loaded),
+                    Err(err) => {
+                        eprintln!("Error: {}", err);
+                        continue;
+                    }
+                }
+            }
+        };
+        let rom = path.to_string_lossy();
+        launcher.played(&path);
+        a.set_watch(watch);
+
+        // Key bindings and macros of the ROM's section, for keyboards and
+        // controllers
+        let options = settings.window_options(&layer);
+        frontend.input.set_keymap(options.keymap);
+        frontend.input.set_macros(options.macros);
+        frontend
+            .renderer
+            .set_palette(layer.palette.unwrap_or_else(|| default_palette.clone()));
+
+        println!("{}", rom);
+        if let Some(info) = a.rom_info() {
+            describe(info);
+        }
+        let name = match a.rom_info() {
+            Some(info) => info.title.clone(),
+            None => path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        };
+        frontend.renderer.set_rom_name(Some(&name));
+
+        // A recording covers the first game, going back to the menu or
+        // opening another ROM ends it
+        let SdlFrontend {
+            renderer,
+            input,
+            audio,
+            ..
+        } = &mut frontend;
+        let outcome = match record_input.take() {
+            Some(path) => {
+                let mut input = MovieRecorder::new(input, &path).unwrap_or_else(|err| {
+                    fail(format!("Cannot create {}: {}", path.display(), err))
+                });
+                let outcome = play(&mut a, renderer, &mut input, audio, &settings, &mut next);
+                input.finish().unwrap_or_else(|err| {
+                    fail(format!("Cannot write {}: {}", path.display(), err))
+                });
+                outcome
+            }
+            None => play(&mut a, renderer, input, audio, &settings, &mut next),
+        };
+        if outcome == RunOutcome::Quit {
+            break;
+        }
+    }
+
+    frontend.renderer.stop_recording();
 }
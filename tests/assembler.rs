@@ -0,0 +1,110 @@
+use lib::assembler::assemble;
+use lib::disassembler::{disassemble, mnemonic};
+
+#[test]
+fn assembles_statements() {
+    let source = "
+        : main
+            clear
+            v0 := 0x0C
+            v1 += 2
+            v2 := random 0xFF
+            v3 |= v4
+            i := hex v0
+            sprite v0 v1 5
+            delay := v0
+            v0 := key
+            bcd v5
+            save v3
+            return
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0x00, 0xE0, 0x60, 0x0C, 0x71, 0x02, 0xC2, 0xFF, 0x83, 0x41, 0xF0, 0x29, 0xD0, 0x15,
+            0xF0, 0x15, 0xF0, 0x0A, 0xF5, 0x33, 0xF3, 0x55, 0x00, 0xEE
+        ]
+    );
+}
+
+#[test]
+fn resolves_forward_labels_and_jumps_to_main() {
+    let source = "
+        : sprite-data 0xF0 0x90
+        : main
+            i := sprite-data
+            draw
+            jump main
+        : draw
+            ;
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0x12, 0x04, 0xF0, 0x90, 0xA2, 0x02, 0x22, 0x0A, 0x12, 0x04, 0x00, 0xEE
+        ]
+    );
+}
+
+#[test]
+fn assembles_structured_control_flow() {
+    let source = "
+        loop
+            while v0 != 5
+            if v1 key begin
+                v0 += 1
+            else
+                v0 := 0
+            end
+        again
+    ";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [
+            0x40, 0x05, 0x12, 0x10, 0xE1, 0xA1, 0x12, 0x0C, 0x70, 0x01, 0x12, 0x0E, 0x60, 0x00,
+            0x12, 0x00
+        ]
+    );
+}
+
+#[test]
+fn constants_and_aliases() {
+    let source = ":const SPEED 3 :alias x v4 x += SPEED if x == SPEED then x := 0";
+    assert_eq!(
+        assemble(source).unwrap(),
+        [0x74, 0x03, 0x44, 0x03, 0x64, 0x00]
+    );
+}
+
+#[test]
+fn errors_point_at_the_line() {
+    let err = assemble("clear\n\nv0 := 300\n").unwrap_err();
+    assert_eq!(err.line, 3);
+
+    let err = assemble("clear\njump nowhere\n").unwrap_err();
+    assert_eq!(err.to_string(), "line 2: undefined name 'nowhere'");
+
+    assert_eq!(assemble("loop\nclear\n").unwrap_err().line, 1);
+    assert!(assemble("v0 := v1 v2").is_err());
+}
+
+#[test]
+fn disassembly_uses_octo_syntax() {
+    assert_eq!(mnemonic(0x8126).as_deref(), Some("v1 >>= v2"));
+    assert_eq!(mnemonic(0xE39E).as_deref(), Some("if v3 -key then"));
+    assert_eq!(mnemonic(0x00FF), None);
+}
+
+#[test]
+fn every_rom_disassembles_and_assembles_back() {
+    for entry in std::fs::read_dir("roms").unwrap() {
+        let path = entry.unwrap().path();
+        let program = std::fs::read(&path).unwrap();
+        assert_eq!(
+            assemble(&disassemble(&program)).unwrap(),
+            program,
+            "{}",
+            path.display()
+        );
+    }
+}
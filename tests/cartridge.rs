@@ -0,0 +1,135 @@
+use lib::assembler;
+use lib::cartridge::{self, CartError, CartOptions, Cartridge};
+use lib::chip::quirks::Quirks;
+use lib::palette::Palette;
+
+fn ibm() -> Vec<u8> {
+    std::fs::read("roms/IBM").unwrap()
+}
+
+#[test]
+fn round_trip_keeps_program_and_options() {
+    let cartridge = Cartridge {
+        program: ibm(),
+        options: CartOptions {
+            tickrate: 20,
+            quirks: Quirks {
+                shift: true,
+                clip: true,
+                vblank: true,
+                ..Quirks::default()
+            },
+            palette: Some(Palette::parse("#112233,#445566,#778899,#AABBCC").unwrap()),
+        },
+    };
+
+    let decoded = cartridge::decode(&cartridge::encode(&cartridge).unwrap()).unwrap();
+
+    assert_eq!(decoded.program, cartridge.program);
+    assert_eq!(decoded.options.tickrate, 20);
+    assert_eq!(decoded.options.quirks, cartridge.options.quirks);
+    assert_eq!(
+        decoded.options.palette.unwrap().colors,
+        cartridge.options.palette.unwrap().colors
+    );
+}
+
+#[test]
+fn large_programs_span_several_frames() {
+    let program: Vec<u8> = (0..3584).map(|i| (i * 7) as u8).collect();
+    let cartridge = Cartridge {
+        program,
+        options: CartOptions::default(),
+    };
+
+    let decoded = cartridge::decode(&cartridge::encode(&cartridge).unwrap()).unwrap();
+    assert_eq!(decoded.program, cartridge.program);
+}
+
+#[test]
+fn byte_listings_assemble() {
+    let source = cartridge::disassemble_bytes(&[0x00, 0xE0, 18, 0b1010, 0xFF]);
+    assert_eq!(
+        assembler::assemble(&source).unwrap(),
+        vec![0x00, 0xE0, 18, 0b1010, 0xFF]
+    );
+}
+
+// A cartridge the way Octo writes them, built without `encode`: the JSON
+// text with its length in front, two bits per pixel of a single frame
+fn octo_gif(program: &str) -> Vec<u8> {
+    let document = serde_json::json!({
+        "options": { "tickrate": 15, "shiftQuirks": true },
+        "program": program,
+    })
+    .to_string();
+    let mut payload = (document.len() as u32).to_be_bytes().to_vec();
+    payload.extend_from_slice(document.as_bytes());
+
+    let mut pixels: Vec<u8> = payload
+        .iter()
+        .flat_map(|b| [b >> 6, (b >> 4) & 3, (b >> 2) & 3, b & 3])
+        .collect();
+    pixels.resize(cartridge::WIDTH * cartridge::HEIGHT, 0);
+
+    let palette = [
+        0, 0, 0, 0x55, 0x55, 0x55, 0xAA, 0xAA, 0xAA, 0xFF, 0xFF, 0xFF,
+    ];
+    let mut out = Vec::new();
+    {
+        let (width, height) = (cartridge::WIDTH as u16, cartridge::HEIGHT as u16);
+        let mut encoder = gif::Encoder::new(&mut out, width, height, &palette).unwrap();
+        let frame = gif::Frame {
+            width,
+            height,
+            buffer: pixels.into(),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&frame).unwrap();
+    }
+    out
+}
+
+#[test]
+fn cartridges_carry_octo_source() {
+    let source = "
+# Draws a sprite and waits for a key
+:alias x v1
+:const SPEED 3
+
+: sprite 0x80 0xC0
+
+: main
+    clear
+    x := SPEED
+    i := sprite
+    loop
+        sprite x x 2
+        v0 := key
+    again
+";
+    let decoded = cartridge::decode(&octo_gif(source)).unwrap();
+
+    assert_eq!(decoded.program, assembler::assemble(source).unwrap());
+    assert_eq!(&decoded.program[..2], &[0x12, 0x04]);
+    assert_eq!(decoded.options.tickrate, 15);
+    assert!(decoded.options.quirks.shift);
+}
+
+#[test]
+fn assembler_errors_reach_the_caller() {
+    let error = cartridge::decode(&octo_gif(": main\n\tjump nowhere\n")).unwrap_err();
+    let CartError::Assembly(error) = error else {
+        panic!("expected an assembler error, got {:?}", error);
+    };
+    assert_eq!(error.line, 2);
+    assert!(error.message.contains("nowhere"));
+}
+
+#[test]
+fn quirks_parse() {
+    let quirks = Quirks::parse("shift,load-store").unwrap();
+    assert!(quirks.shift && quirks.load_store && !quirks.clip);
+    assert_eq!(Quirks::parse("none").unwrap(), Quirks::default());
+    assert!(Quirks::parse("bogus").is_err());
+}
@@ -0,0 +1,206 @@
+use std::path::Path;
+
+use lib::chip::Chip;
+use lib::config::{Config, Layer};
+use lib::frontend::keymap::KeyMap;
+use lib::frontend::sdl::SdlOptions;
+use lib::graphics::Protocol;
+use lib::palette::Palette;
+use lib::render::crt::CrtSettings;
+
+const CONFIG: &str = r##"
+palette = "amber"
+ipf = 15
+
+[keys]
+5 = ["W", "Up"]
+
+[rom.BRIX]
+ipf = 20
+quirks = ["shift", "clip"]
+
+[rom."ffd7a6b8d4e3e2b2c4b0e4e0c7f4a8c9f1a4b6d2"]
+ipf = 30
+"##;
+
+#[test]
+fn rom_sections_override_the_whole_file() {
+    let config = Config::parse(CONFIG, "config.toml").unwrap();
+
+    let layer = Layer::merged(&config.layers(Some(Path::new("roms/BRIX")), None));
+    assert_eq!(layer.ipf, Some(20));
+    assert_eq!(layer.palette, Palette::named("amber"));
+    assert!(layer.quirks.unwrap().shift);
+
+    let layer = Layer::merged(&config.layers(Some(Path::new("roms/PONG")), None));
+    assert_eq!(layer.ipf, Some(15));
+    assert_eq!(layer.quirks, None);
+}
+
+#[test]
+fn sections_match_by_sha1() {
+    let text = CONFIG.replace(
+        "ffd7a6b8d4e3e2b2c4b0e4e0c7f4a8c9f1a4b6d2",
+        &lib::database::sha1(b"\x12\x00"),
+    );
+    let config = Config::parse(&text, "config.toml").unwrap();
+
+    let layers = config.layers(Some(Path::new("loop.ch8")), Some(b"\x12\x00"));
+    assert_eq!(Layer::merged(&layers).ipf, Some(30));
+}
+
+#[test]
+fn later_layers_win_and_apply_to_the_chip() {
+    let config = Config::parse(CONFIG, "config.toml").unwrap();
+    let mut cli = Layer::new("command line");
+    cli.set("ipf", &toml::Value::Integer(7)).unwrap();
+    cli.set("platform", &"chip48".into()).unwrap();
+
+    let mut layers = config.layers(Some(Path::new("BRIX")), None);
+    layers.push(cli);
+    let layer = Layer::merged(&layers);
+
+    let mut chip = Chip::new();
+    layer.apply(&mut chip);
+    assert_eq!(chip.tickrate(), 7);
+    // The platform of the command line wins over the quirks of the section
+    assert!(chip.quirks().shift && chip.quirks().load_store);
+}
+
+// The quirks a chip ends up with from a config file and command line flags
+fn quirks(file: &str, cli: &[(&str, &str)]) -> String {
+    let config = Config::parse(file, "config.toml").unwrap();
+    let mut layer = Layer::new("command line");
+    for (key, value) in cli {
+        layer.set(key, &(*value).into()).unwrap();
+    }
+    let mut layers = config.layers(None, None);
+    layers.push(layer);
+
+    let mut chip = Chip::new();
+    Layer::merged(&layers).apply(&mut chip);
+    chip.quirks().to_spec()
+}
+
+#[test]
+fn platform_and_quirks_follow_the_layer_order() {
+    let file = "quirks = \"logic\"\n";
+    assert_eq!(quirks(file, &[]), "logic");
+    assert_eq!(
+        quirks(file, &[("platform", "chip48")]),
+        "shift,load-store,jump,clip"
+    );
+    // Quirks given by name win over a platform of the same layer
+    assert_eq!(
+        quirks(file, &[("platform", "chip48"), ("quirks", "jump")]),
+        "jump"
+    );
+
+    let file = "platform = \"chip48\"\n";
+    assert_eq!(quirks(file, &[("quirks", "vblank")]), "vblank");
+}
+
+#[test]
+fn window_settings_and_keys() {
+    let config = Config::parse(CONFIG, "config.toml").unwrap();
+    let mut options = SdlOptions::default();
+    config.base.apply_window(&mut options);
+
+    assert_eq!(options.palette, Palette::named("amber").unwrap());
+    assert_eq!(options.keymap.key("up"), Some(5));
+    assert_eq!(options.keymap.key("W"), Some(5));
+    assert_eq!(options.keymap.key("Q"), Some(4));
+}
+
+#[test]
+fn crt_table_sets_effect_strengths() {
+    let text = "crt = false\n[rom.BRIX.crt]\nbloom = 0.5\ncurvature = 0\n";
+    let config = Config::parse(text, "config.toml").unwrap();
+
+    let mut options = SdlOptions::default();
+    Layer::merged(&config.layers(Some(Path::new("BRIX")), None)).apply_window(&mut options);
+    let crt = options.crt.unwrap();
+    assert_eq!((crt.bloom, crt.curvature), (0.5, 0.0));
+    assert_eq!(crt.scanlines, CrtSettings::default().scanlines);
+
+    let mut options = SdlOptions::default();
+    Layer::merged(&config.layers(Some(Path::new("PONG")), None)).apply_window(&mut options);
+    assert_eq!(options.crt, None);
+
+    let err = Config::parse("[crt]\nbloom = 3\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "crt.bloom");
+    let err = Config::parse("[crt]\nglow = 1\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "crt.glow");
+}
+
+#[test]
+fn terminal_settings() {
+    let text = "terminal = \"kitty\"\nterminal-scale = 2\n[rom.BRIX]\nterminal = \"none\"\n";
+    let config = Config::parse(text, "config.toml").unwrap();
+
+    let mut options = SdlOptions::default();
+    config.base.apply_window(&mut options);
+    assert_eq!(options.terminal, Some(Protocol::Kitty));
+    assert_eq!(options.terminal_scale, 2);
+
+    let mut options = SdlOptions::default();
+    Layer::merged(&config.layers(Some(Path::new("BRIX")), None)).apply_window(&mut options);
+    assert_eq!(options.terminal, None);
+
+    let err = Config::parse("terminal = \"vt100\"\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "terminal");
+    let err = Config::parse("terminal-scale = 0\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "terminal-scale");
+}
+
+#[test]
+fn errors_name_the_offending_key() {
+    let err = Config::parse("[rom.BRIX]\nipf = 0\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "rom.\"BRIX\".ipf");
+
+    let err = Config::parse("speed = 2\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "speed");
+
+    let err = Config::parse("[keys]\nG = \"Q\"\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "keys.G");
+
+    let err = Config::parse("palette = \"mauve\"\n", "config.toml").unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with("config.toml: palette: unknown palette")
+    );
+
+    let err = Config::parse("scale = \n", "config.toml").unwrap_err();
+    assert!(err.to_string().contains("line 1"));
+}
+
+#[test]
+fn hotkeys_cannot_be_bound() {
+    let err = Config::parse("[keys]\n5 = [\"W\", \"f5\"]\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "keys.5");
+    assert!(err.message.starts_with("F5 is a hotkey"), "{}", err.message);
+
+    let err = Config::parse("[rom.BRIX.autofire]\n6 = \"tab\"\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "rom.\"BRIX\".autofire.6");
+    assert!(
+        err.message.starts_with("Tab is a hotkey"),
+        "{}",
+        err.message
+    );
+
+    let err = Config::parse("[macros]\np = [\"4\"]\n", "config.toml").unwrap_err();
+    assert_eq!(err.key, "macros.\"p\"");
+    assert!(err.message.starts_with("P is a hotkey"), "{}", err.message);
+
+    // F8 and F10 are free
+    assert!(Config::parse("[keys]\n5 = \"F8\"\n[macros]\nF10 = \"4\"\n", "config.toml").is_ok());
+}
+
+#[test]
+fn default_keymap_follows_the_cosmac_layout() {
+    let keymap = KeyMap::default();
+    assert_eq!(keymap.key("x"), Some(0x0));
+    assert_eq!(keymap.key("4"), Some(0xC));
+    assert_eq!(keymap.key("V"), Some(0xF));
+    assert_eq!(keymap.key("P"), None);
+}
@@ -0,0 +1,67 @@
+use lib::image::{Image, Rgb};
+use lib::render::crt::{self, CrtSettings, EFFECTS};
+
+const GREY: Rgb = Rgb(0x80, 0x80, 0x80);
+const WHITE: Rgb = Rgb(0xFF, 0xFF, 0xFF);
+
+const OFF: CrtSettings = CrtSettings {
+    scanlines: 0.0,
+    bloom: 0.0,
+    vignette: 0.0,
+    curvature: 0.0,
+};
+
+// A grey picture with a white square in the middle
+fn picture() -> Image {
+    let mut image = Image::new(64, 32, GREY);
+    for y in 12..20 {
+        for x in 28..36 {
+            image.set(x, y, WHITE);
+        }
+    }
+    image
+}
+
+fn only(name: &str, strength: f32) -> CrtSettings {
+    let mut settings = OFF;
+    *settings.effect_mut(name).unwrap() = strength;
+    settings
+}
+
+#[test]
+fn zero_strengths_change_nothing() {
+    let image = picture();
+    assert_eq!(crt::apply(&image, &OFF), image);
+    for (name, _) in EFFECTS {
+        assert_eq!(crt::apply(&image, &only(name, 0.0)), image, "{}", name);
+    }
+}
+
+#[test]
+fn every_effect_changes_the_picture() {
+    let image = picture();
+    for (name, max) in EFFECTS {
+        assert_ne!(crt::apply(&image, &only(name, max)), image, "{}", name);
+    }
+}
+
+#[test]
+fn effects_do_what_they_say() {
+    let image = picture();
+
+    let shown = crt::apply(&image, &only("scanlines", 0.5));
+    assert_eq!(shown.get(0, 0), GREY);
+    assert_eq!(shown.get(0, 1), Rgb(0x40, 0x40, 0x40));
+
+    // Light spills next to the white square
+    let shown = crt::apply(&image, &only("bloom", 0.5));
+    assert!(shown.get(27, 15).0 > shown.get(0, 0).0);
+
+    let shown = crt::apply(&image, &only("vignette", 1.0));
+    assert!(shown.get(0, 0).0 < shown.get(31, 16).0);
+
+    // The corners fall off the bent tube
+    let shown = crt::apply(&image, &only("curvature", 0.5));
+    assert_eq!(shown.get(0, 0), Rgb(0, 0, 0));
+    assert_eq!(shown.get(32, 16), WHITE);
+}
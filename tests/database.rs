@@ -0,0 +1,72 @@
+use lib::chip::Chip;
+use lib::database::{self, Database};
+
+#[test]
+fn bundled_database_covers_every_rom() {
+    let database = Database::bundled();
+
+    for entry in std::fs::read_dir("roms").unwrap() {
+        let path = entry.unwrap().path();
+        let program = std::fs::read(&path).unwrap();
+        assert!(
+            database.lookup(&program).is_some(),
+            "{} is missing from the database",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn sha1_matches_known_digest() {
+    assert_eq!(
+        database::sha1(b"abc"),
+        "a9993e364706816aba3e25717850c26c9cd0d89d"
+    );
+}
+
+#[test]
+fn load_program_applies_database_settings() {
+    let mut chip = Chip::new();
+    chip.load_program("roms/BLITZ").unwrap();
+
+    let info = chip.rom_info().unwrap();
+    assert_eq!(info.title, "Blitz");
+    assert!(chip.quirks().clip);
+    assert_eq!(chip.tickrate(), 15);
+}
+
+#[test]
+fn quirky_platforms_override_platform_defaults() {
+    let json = r##"[{
+        "title": "Test",
+        "authors": ["Someone"],
+        "roms": {
+            "A9993E364706816ABA3E25717850C26C9CD0D89D": {
+                "platforms": ["originalChip8"],
+                "quirkyPlatforms": {"originalChip8": {"wrap": true, "shift": true}},
+                "tickrate": 20,
+                "colors": {"pixels": ["#000000", "#FF0000"]},
+                "keys": {"a": 5}
+            }
+        }
+    }]"##;
+
+    let database = Database::parse(json).unwrap();
+    let info = database.lookup(b"abc").unwrap();
+    let quirks = info.quirks.unwrap();
+
+    assert!(quirks.shift && quirks.vblank && quirks.logic);
+    assert!(!quirks.clip);
+    assert_eq!(info.tickrate, Some(20));
+    assert_eq!(info.keys, vec![("a".to_string(), 5)]);
+    assert!(info.palette.is_some());
+}
+
+#[test]
+fn unknown_platforms_leave_quirks_alone() {
+    let json = r#"[{"title": "T", "roms": {"00": {"platforms": ["megachip8"]}}}]"#;
+    assert_eq!(
+        Database::parse(json).unwrap().get("00").unwrap().quirks,
+        None
+    );
+}
@@ -0,0 +1,65 @@
+use lib::chip::{Chip, Fault};
+use lib::frontend::KeypadState;
+use lib::frontend::headless::{HeadlessRenderer, NullAudio, NullInput};
+
+fn run(program: &[u8]) -> Result<(), Fault> {
+    let mut chip = Chip::new();
+    chip.load_bytes(program).unwrap();
+    chip.try_run_frame(KeypadState::default())
+}
+
+#[test]
+fn programs_stop_on_instructions_that_cannot_run() {
+    assert_eq!(
+        run(&[0x60, 0x01, 0x01, 0x23]),
+        Err(Fault::UnknownInstruction(0x0123))
+    );
+    assert_eq!(run(&[0x00, 0xEE]), Err(Fault::EmptyStack));
+    // Sprite data read past the end of the memory
+    assert_eq!(
+        run(&[0xAF, 0xFE, 0xD0, 0x05]),
+        Err(Fault::OutOfMemory(0x0FFE))
+    );
+    // Running off the end of the memory
+    assert_eq!(run(&[0x1F, 0xFF]), Err(Fault::OutOfMemory(0x0FFF)));
+
+    // A loop runs the whole frame
+    assert_eq!(run(&[0x12, 0x00]), Ok(()));
+}
+
+#[test]
+fn faults_leave_the_program_where_it_stopped() {
+    let mut chip = Chip::new();
+    chip.load_bytes(&[0x00, 0xEE, 0x12, 0x00]).unwrap();
+    assert_eq!(chip.try_instruction(), Err(Fault::EmptyStack));
+    assert_eq!(chip.try_instruction(), Err(Fault::EmptyStack));
+
+    chip.load_bytes(&[0x01, 0x23, 0x12, 0x00]).unwrap();
+    assert_eq!(
+        chip.try_instruction(),
+        Err(Fault::UnknownInstruction(0x0123))
+    );
+    assert_eq!(
+        chip.try_instruction(),
+        Err(Fault::UnknownInstruction(0x0123))
+    );
+}
+
+#[test]
+fn steps_return_the_fault_after_showing_the_screen() {
+    let mut chip = Chip::new();
+    // Clears the screen, then runs into an unknown instruction
+    chip.load_bytes(&[0x00, 0xE0, 0xFF, 0xFF]).unwrap();
+    let mut renderer = HeadlessRenderer::default();
+    let result = chip.step(&mut renderer, &mut NullInput, &mut NullAudio);
+    assert_eq!(result, Err(Fault::UnknownInstruction(0xFFFF)));
+    assert_eq!(renderer.frames, 1);
+}
+
+#[test]
+#[should_panic(expected = "Instruction 0xffff do not exists!")]
+fn running_frames_still_panics_on_faults() {
+    let mut chip = Chip::new();
+    chip.load_bytes(&[0xFF, 0xFF]).unwrap();
+    chip.run_frame(KeypadState::default());
+}
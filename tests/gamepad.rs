@@ -0,0 +1,110 @@
+use std::sync::Mutex;
+
+use lib::frontend::InputSource;
+use lib::frontend::gamepad::{DEADZONE, PadState};
+use lib::frontend::keymap::KeyMap;
+
+#[test]
+fn buttons_and_sticks_press_bound_keys() {
+    let keymap = KeyMap::default();
+    let mut pad = PadState::default();
+
+    pad.button("dpup", true);
+    pad.axis("leftx", -DEADZONE);
+    let keypad = pad.keypad(&keymap);
+    assert!(keypad.is_pressed(0x5) && keypad.is_pressed(0x7));
+
+    pad.button("dpup", false);
+    pad.axis("leftx", DEADZONE - 1);
+    assert_eq!(pad.keypad(&keymap).first_pressed(), None);
+
+    pad.axis("lefty", i16::MAX);
+    pad.button("a", true);
+    let keypad = pad.keypad(&keymap);
+    assert!(keypad.is_pressed(0x8) && keypad.is_pressed(0x6));
+}
+
+#[test]
+fn controllers_follow_the_key_bindings() {
+    let mut keymap = KeyMap::default();
+    keymap.bind(0xF, vec!["pad:rightshoulder".to_string()]);
+    let mut pad = PadState::default();
+
+    pad.button("rightshoulder", true);
+    assert_eq!(pad.keypad(&keymap).first_pressed(), Some(0xF));
+    assert_eq!(pad.keypad(&KeyMap::default()).first_pressed(), None);
+}
+
+// SDL can only be initialised once at a time, tests using it take turns
+static SDL: Mutex<()> = Mutex::new(());
+
+// Drives the SDL frontend with a virtual controller, no hardware needed
+#[test]
+fn virtual_controller_drives_the_sdl_input() {
+    use std::ffi::{CString, c_char};
+
+    use lib::frontend::sdl::{SdlFrontend, SdlOptions};
+    use sdl2::{hint, sys};
+
+    let _sdl = SDL.lock().unwrap_or_else(|err| err.into_inner());
+    // Hints rather than environment variables, which other threads may read.
+    // The dummy window never has focus, where SDL drops controller input.
+    hint::set("SDL_VIDEODRIVER", "dummy");
+    hint::set("SDL_AUDIODRIVER", "dummy");
+    hint::set("SDL_JOYSTICK_ALLOW_BACKGROUND_EVENTS", "1");
+    let mut frontend = SdlFrontend::new(SdlOptions::default());
+
+    // SAFETY: plain SDL calls on a virtual device this test owns
+    let (joystick, index) = unsafe {
+        let attach = || {
+            sys::SDL_JoystickAttachVirtual(
+                sys::SDL_JoystickType::SDL_JOYSTICK_TYPE_GAMECONTROLLER,
+                6,
+                15,
+                0,
+            )
+        };
+
+        // Map the virtual device, then plug it in again so that SDL sees a
+        // game controller
+        let index = attach();
+        assert!(index >= 0);
+        let mut guid = [0 as c_char; 33];
+        sys::SDL_JoystickGetGUIDString(
+            sys::SDL_JoystickGetDeviceGUID(index),
+            guid.as_mut_ptr(),
+            guid.len() as i32,
+        );
+        let guid = std::ffi::CStr::from_ptr(guid.as_ptr()).to_string_lossy();
+        let mapping = CString::new(format!(
+            "{},Virtual,a:b0,b:b1,x:b2,y:b3,back:b4,guide:b5,start:b6,leftstick:b7,\
+             rightstick:b8,leftshoulder:b9,rightshoulder:b10,dpup:b11,dpdown:b12,\
+             dpleft:b13,dpright:b14,leftx:a0,lefty:a1,rightx:a2,righty:a3,\
+             lefttrigger:a4,righttrigger:a5,",
+            guid
+        ))
+        .unwrap();
+        assert!(sys::SDL_GameControllerAddMapping(mapping.as_ptr()) >= 0);
+        sys::SDL_JoystickDetachVirtual(index);
+
+        let index = attach();
+        (sys::SDL_JoystickOpen(index), index)
+    };
+    assert!(!joystick.is_null());
+    frontend.input.poll();
+
+    // D-pad up and the left stick pushed left
+    unsafe {
+        sys::SDL_JoystickSetVirtualButton(joystick, 11, 1);
+        sys::SDL_JoystickSetVirtualAxis(joystick, 0, i16::MIN);
+    }
+    let keypad = frontend.input.poll();
+    assert!(keypad.is_pressed(0x5) && keypad.is_pressed(0x7));
+
+    // Unplugging releases everything
+    unsafe {
+        sys::SDL_JoystickClose(joystick);
+        sys::SDL_JoystickDetachVirtual(index);
+    }
+    assert_eq!(frontend.input.poll().first_pressed(), None);
+}
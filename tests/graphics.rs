@@ -0,0 +1,71 @@
+use lib::display::{HEIGHT, WIDTH};
+use lib::graphics::{Protocol, TerminalGraphics};
+use lib::image::Rgb;
+
+// The "0" glyph of the built-in font at the top left corner and a single
+// pixel in the bottom right one
+fn known_data() -> [bool; WIDTH * HEIGHT] {
+    let mut data = [false; WIDTH * HEIGHT];
+    let glyph: [u8; 5] = [0xF0, 0x90, 0x90, 0x90, 0xF0];
+
+    for (row, bits) in glyph.iter().enumerate() {
+        for col in 0..8 {
+            if bits & (0x80 >> col) != 0 {
+                data[row * WIDTH + col] = true;
+            }
+        }
+    }
+    data[WIDTH * HEIGHT - 1] = true;
+
+    data
+}
+
+#[test]
+fn sixel_matches_fixture() {
+    let graphics = TerminalGraphics {
+        protocol: Protocol::Sixel,
+        scale: 1,
+        foreground: Rgb(0xFF, 0xAA, 0x00),
+        background: Rgb::BLACK,
+    };
+
+    assert_eq!(
+        graphics.encode(&known_data(), WIDTH, HEIGHT),
+        include_str!("fixtures/known_scale1.sixel")
+    );
+}
+
+#[test]
+fn sixel_scaled_matches_fixture() {
+    let mut graphics = TerminalGraphics::new(Protocol::Sixel);
+    graphics.scale = 2;
+
+    assert_eq!(
+        graphics.encode(&known_data(), WIDTH, HEIGHT),
+        include_str!("fixtures/known_scale2.sixel")
+    );
+}
+
+#[test]
+fn kitty_matches_fixture() {
+    let graphics = TerminalGraphics {
+        protocol: Protocol::Kitty,
+        scale: 1,
+        foreground: Rgb(0xFF, 0xAA, 0x00),
+        background: Rgb::BLACK,
+    };
+
+    assert_eq!(
+        graphics.encode(&known_data(), WIDTH, HEIGHT),
+        include_str!("fixtures/known_scale1.kitty")
+    );
+}
+
+#[test]
+fn protocols_by_name() {
+    for protocol in [Protocol::Sixel, Protocol::Kitty] {
+        assert_eq!(Protocol::parse(protocol.name()), Ok(protocol));
+    }
+    assert_eq!(Protocol::parse("Kitty"), Ok(Protocol::Kitty));
+    assert!(Protocol::parse("iterm").is_err());
+}
@@ -0,0 +1,57 @@
+use std::fs;
+use std::path::PathBuf;
+
+use lib::display::Display;
+use lib::frontend::Renderer;
+use lib::frontend::image_sequence::ImageSequenceRenderer;
+use lib::palette::Palette;
+use lib::render::Pipeline;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_chip8_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn every_presented_frame_is_written() {
+    let dir = temp_dir("sequence");
+    let mut renderer =
+        ImageSequenceRenderer::new(&dir, Pipeline::new(Palette::default(), None)).unwrap();
+    let mut display = Display::new();
+    renderer.present(&display);
+    display.set_pixel(0, 0, true);
+    renderer.present(&display);
+
+    assert_eq!(renderer.frames(), 2);
+    renderer.finish().unwrap();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    let header = b"P6\n64 32\n255\n";
+    let second = fs::read(dir.join("frame_000001.ppm")).unwrap();
+    assert!(second.starts_with(header));
+    assert_eq!(second.len(), header.len() + 64 * 32 * 3);
+    assert_eq!(second[header.len()..header.len() + 3], [255, 255, 255]);
+}
+
+#[test]
+fn unwritable_directories_are_errors() {
+    let dir = temp_dir("sequence_file");
+    fs::write(&dir, "not a directory").unwrap();
+    let pipeline = Pipeline::new(Palette::default(), None);
+    assert!(ImageSequenceRenderer::new(dir.join("frames"), pipeline).is_err());
+    fs::remove_file(&dir).unwrap();
+}
+
+#[test]
+fn write_errors_are_kept_for_finish() {
+    let dir = temp_dir("sequence_gone");
+    let mut renderer =
+        ImageSequenceRenderer::new(&dir, Pipeline::new(Palette::default(), None)).unwrap();
+    fs::remove_dir(&dir).unwrap();
+
+    renderer.present(&Display::new());
+    renderer.present(&Display::new());
+    assert_eq!(renderer.frames(), 0);
+    assert!(renderer.finish().is_err());
+}
@@ -0,0 +1,55 @@
+use lib::frontend::KeypadState;
+use lib::image::{Image, Rgb};
+use lib::render::keypad::{self, LAYOUT};
+
+const OFF: Rgb = Rgb(0, 0, 0);
+const ON: Rgb = Rgb(0xFF, 0xFF, 0xFF);
+
+#[test]
+fn keypad_goes_next_to_the_picture() {
+    let frame = Image::new(64, 32, ON);
+    let shown = keypad::draw(&frame, KeypadState::default(), OFF, ON);
+
+    assert!(shown.height >= keypad::MIN_HEIGHT);
+    assert_eq!(shown.width, 3 * shown.height);
+    assert_eq!(shown.get(0, 0), ON);
+    assert_eq!(shown.get(2 * shown.height - 1, shown.height - 1), ON);
+}
+
+#[test]
+fn clicks_find_the_key_under_them() {
+    let frame = Image::new(64, 32, OFF);
+    let shown = keypad::draw(&frame, KeypadState::default(), OFF, ON);
+    let (width, height) = (shown.width, shown.height);
+    let cell = height / 4;
+    let left = width - height;
+
+    for (row, keys) in LAYOUT.iter().enumerate() {
+        for (column, &key) in keys.iter().enumerate() {
+            let x = left + column * cell + cell / 2;
+            let y = row * cell + cell / 2;
+            assert_eq!(keypad::key_at(width, height, x, y), Some(key));
+        }
+    }
+    assert_eq!(keypad::key_at(width, height, left - 1, 0), None);
+    assert_eq!(keypad::key_at(width, height, width, 0), None);
+    assert_eq!(keypad::key_at(width, height, left, height), None);
+}
+
+#[test]
+fn pressed_keys_light_up() {
+    let frame = Image::new(64, 32, OFF);
+    let mut pressed = KeypadState::default();
+    pressed.press(0x5);
+    let shown = keypad::draw(&frame, pressed, OFF, ON);
+
+    let cell = shown.height / 4;
+    let left = shown.width - shown.height;
+    // Near the corner of a key, away from its label
+    let face = |row: usize, column: usize| {
+        shown.get(left + column * cell + cell / 4, row * cell + cell / 4)
+    };
+    assert_eq!(face(1, 1), ON);
+    assert_ne!(face(0, 0), ON);
+    assert_ne!(face(0, 0), OFF);
+}
@@ -0,0 +1,110 @@
+use std::path::{Path, PathBuf};
+
+use lib::chip::MAX_PROGRAM_SIZE;
+use lib::frontend::Action;
+use lib::launcher::library::{self, LibraryState};
+use lib::launcher::{Launcher, MENU_HEIGHT, MENU_WIDTH, View};
+use lib::palette::Palette;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rust_chip8_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn library_state_round_trips() {
+    let mut state = LibraryState::default();
+    state.toggle_favourite(Path::new("/roms/BRIX"));
+    state.played(Path::new("/roms/PONG"));
+    state.played(Path::new("/roms/BRIX"));
+
+    let parsed = LibraryState::parse(&state.to_text());
+    assert_eq!(parsed, state);
+    assert!(parsed.is_favourite(Path::new("/roms/BRIX")));
+    assert_eq!(
+        parsed.recent,
+        vec![PathBuf::from("/roms/BRIX"), PathBuf::from("/roms/PONG")]
+    );
+}
+
+#[test]
+fn toggling_a_favourite_twice_removes_it() {
+    let mut state = LibraryState::default();
+    state.toggle_favourite(Path::new("/roms/BRIX"));
+    state.toggle_favourite(Path::new("/roms/BRIX"));
+    assert!(state.favourites.is_empty());
+}
+
+#[test]
+fn recent_list_is_capped_and_deduplicated() {
+    let mut state = LibraryState::default();
+    for i in 0..15 {
+        state.played(Path::new(&format!("/roms/{}", i)));
+    }
+    state.played(Path::new("/roms/12"));
+
+    assert_eq!(state.recent.len(), 10);
+    assert_eq!(state.recent[0], PathBuf::from("/roms/12"));
+    assert_eq!(state.recent[1], PathBuf::from("/roms/14"));
+    assert_eq!(state.recent.iter().filter(|p| p.ends_with("12")).count(), 1);
+}
+
+#[test]
+fn scan_uses_database_titles_and_skips_oversized_files() {
+    let dir = temp_dir("scan");
+    std::fs::copy("roms/IBM", dir.join("ibm.ch8")).unwrap();
+    std::fs::write(dir.join("unknown.ch8"), [0x00, 0xE0]).unwrap();
+    std::fs::write(dir.join("huge.bin"), vec![0; MAX_PROGRAM_SIZE + 1]).unwrap();
+
+    let titles: Vec<String> = library::scan(&dir)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.title)
+        .collect();
+    assert_eq!(titles, ["IBM Logo", "unknown.ch8"]);
+}
+
+#[test]
+fn launcher_picks_and_favourites_roms() {
+    let dir = temp_dir("launcher");
+    std::fs::copy("roms/IBM", dir.join("ibm.ch8")).unwrap();
+    std::fs::write(dir.join("unknown.ch8"), [0x12, 0x00]).unwrap();
+
+    let mut launcher =
+        Launcher::with_state(&dir, Palette::default(), LibraryState::default(), None);
+    assert_eq!(launcher.handle(Action::Down), None);
+    launcher.handle(Action::ToggleFavourite);
+
+    let picked = launcher.handle(Action::Select).unwrap();
+    assert!(picked.ends_with("unknown.ch8"));
+
+    launcher.handle(Action::NextList);
+    assert_eq!(launcher.view(), View::Favourites);
+    assert_eq!(launcher.entries().len(), 1);
+
+    let image = launcher.render();
+    assert_eq!((image.width, image.height), (MENU_WIDTH, MENU_HEIGHT));
+}
+
+#[test]
+fn broken_roms_get_no_thumbnail() {
+    let dir = temp_dir("broken");
+    std::fs::write(dir.join("broken.ch8"), [0x00, 0xEE]).unwrap();
+
+    let mut launcher =
+        Launcher::with_state(&dir, Palette::default(), LibraryState::default(), None);
+    let image = launcher.render();
+    assert_eq!((image.width, image.height), (MENU_WIDTH, MENU_HEIGHT));
+}
+
+#[test]
+fn library_errors_are_kept_for_the_menu() {
+    let dir = temp_dir("missing").join("nowhere");
+    let mut launcher =
+        Launcher::with_state(&dir, Palette::default(), LibraryState::default(), None);
+    assert!(launcher.error().unwrap().starts_with("Cannot read "));
+    assert!(launcher.entries().is_empty());
+    launcher.render();
+}
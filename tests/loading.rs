@@ -0,0 +1,62 @@
+use lib::chip::rom::{self, LoadError};
+use lib::chip::{Chip, MAX_PROGRAM_SIZE};
+
+#[test]
+fn load_bytes_accepts_the_largest_program() {
+    let mut chip = Chip::new();
+    let program = vec![0xAB; MAX_PROGRAM_SIZE];
+    chip.load_bytes(&program).unwrap();
+    assert_eq!(chip.program(), &program[..]);
+}
+
+#[test]
+fn load_bytes_rejects_oversized_programs() {
+    let mut chip = Chip::new();
+    let err = chip.load_bytes(&vec![0; MAX_PROGRAM_SIZE + 1]).unwrap_err();
+
+    assert!(matches!(
+        err,
+        LoadError::TooLarge {
+            size: 3585,
+            max: 3584
+        }
+    ));
+    assert!(err.to_string().contains("ROM too large for platform"));
+}
+
+#[test]
+fn load_reader_matches_load_program() {
+    let bytes = std::fs::read("roms/IBM").unwrap();
+
+    let mut from_file = Chip::new();
+    from_file.load_program("roms/IBM").unwrap();
+    let mut from_reader = Chip::new();
+    from_reader.load_reader(&bytes[..]).unwrap();
+
+    assert_eq!(from_file.program(), from_reader.program());
+    assert_eq!(from_reader.program(), &bytes[..]);
+}
+
+#[test]
+fn missing_files_are_errors() {
+    let mut chip = Chip::new();
+    assert!(matches!(
+        chip.load_program("roms/does-not-exist"),
+        Err(LoadError::Io(_))
+    ));
+}
+
+#[test]
+fn hex_listings_parse() {
+    let text = "# IBM logo start\n0200: 00E0 A22A\n0x60 0x0C ; V0 = 12\n6108 // V1 = 8\n";
+    assert_eq!(
+        rom::parse_hex(text).unwrap(),
+        vec![0x00, 0xE0, 0xA2, 0x2A, 0x60, 0x0C, 0x61, 0x08]
+    );
+}
+
+#[test]
+fn hex_listings_report_bad_tokens() {
+    let err = rom::parse_hex("00E0\nA2G\n").unwrap_err();
+    assert!(matches!(err, LoadError::Hex { line: 2, .. }));
+}
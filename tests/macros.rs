@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use lib::config::{Config, Layer};
+use lib::frontend::KeypadState;
+use lib::frontend::macros::{Macro, MacroMap, Macros};
+use lib::frontend::sdl::SdlOptions;
+
+fn keys(keys: &[u8]) -> KeypadState {
+    let mut keypad = KeypadState::default();
+    for &key in keys {
+        keypad.press(key);
+    }
+    keypad
+}
+
+fn steps(steps: &[&str]) -> Macro {
+    let steps: Vec<String> = steps.iter().map(|step| step.to_string()).collect();
+    Macro::parse(&steps).unwrap()
+}
+
+#[test]
+fn macros_play_one_step_per_frame() {
+    let mut map = MacroMap::default();
+    map.bind_macro("G", steps(&["4", "", "5+6*2"]));
+    let mut macros = Macros::new(map);
+
+    assert_eq!(macros.apply(keys(&[])), keys(&[]));
+    macros.host_key("g");
+    let played: Vec<KeypadState> = (0..5).map(|_| macros.apply(keys(&[0x8]))).collect();
+    assert_eq!(
+        played,
+        [
+            keys(&[0x4, 0x8]),
+            keys(&[0x8]),
+            keys(&[0x5, 0x6, 0x8]),
+            keys(&[0x5, 0x6, 0x8]),
+            keys(&[0x8]),
+        ]
+    );
+}
+
+#[test]
+fn invalid_steps_are_refused() {
+    for step in ["G", "5+", "5*0", "5*x", "10"] {
+        assert!(Macro::parse(&[step.to_string()]).is_err(), "{}", step);
+    }
+    assert_eq!(steps(&["-*3", "1+2"]).len(), 4);
+}
+
+#[test]
+fn autofire_repeats_held_keys_until_toggled_off() {
+    let mut map = MacroMap::default();
+    map.bind_autofire(0x6, vec!["T".to_string()]);
+    map.autofire_rate = 2;
+    let mut macros = Macros::new(map);
+
+    assert_eq!(macros.host_key("T"), ["Autofire 6 on"]);
+    let pressed: Vec<bool> = (0..6)
+        .map(|_| macros.apply(keys(&[0x6])).is_pressed(0x6))
+        .collect();
+    assert_eq!(pressed, [true, true, false, false, true, true]);
+    // Keys without autofire stay held
+    assert!(macros.apply(keys(&[0x5])).is_pressed(0x5));
+
+    assert_eq!(macros.host_key("t"), ["Autofire 6 off"]);
+    assert!((0..4).all(|_| macros.apply(keys(&[0x6])).is_pressed(0x6)));
+}
+
+#[test]
+fn macros_and_autofire_come_from_the_config() {
+    let text = r#"
+autofire-rate = 3
+
+[autofire]
+6 = "T"
+
+[macros]
+G = ["4", "5*2"]
+
+[rom.BRIX.macros]
+G = ["6"]
+H = "-"
+"#;
+    let config = Config::parse(text, "config.toml").unwrap();
+    let layer = Layer::merged(&config.layers(Some(Path::new("roms/BRIX")), None));
+    let mut options = SdlOptions::default();
+    layer.apply_window(&mut options);
+
+    assert_eq!(options.macros.autofire_rate, 3);
+    assert_eq!(options.macros.autofire(0x6), ["T"]);
+    assert_eq!(options.macros.macros()["G"], steps(&["6"]));
+    assert!(options.macros.macros().contains_key("H"));
+
+    let error = Config::parse("[macros]\nG = [\"4\", \"Z\"]\n", "config.toml").unwrap_err();
+    assert_eq!(error.key, "macros.\"G\"");
+}
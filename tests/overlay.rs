@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+use lib::image::{Image, Rgb};
+use lib::render::overlay::{self, FrameRate, Overlay};
+
+const BLACK: Rgb = Rgb(0, 0, 0);
+
+fn lit(image: &Image, x: usize, y: usize, width: usize, height: usize) -> bool {
+    (y..y + height).any(|y| (x..x + width).any(|x| image.get(x, y) != BLACK))
+}
+
+#[test]
+fn overlay_is_drawn_on_a_copy() {
+    let frame = Image::new(64, 32, BLACK);
+    let mut overlay = Overlay::default();
+    overlay.error = Some("line 3: undefined name 'nowhere'".to_string());
+
+    let shown = overlay.draw(&frame, Instant::now());
+    assert!(shown.width >= overlay::MIN_WIDTH);
+    assert_ne!(shown.get(0, shown.height - 1), BLACK);
+    assert_eq!(frame.get(0, 31), BLACK);
+}
+
+#[test]
+fn stats_and_status_go_in_the_corners() {
+    let frame = Image::new(64, 32, BLACK);
+    let mut overlay = Overlay::default();
+    overlay.stats = Some("60 FPS 100%".to_string());
+    overlay.status = Some("Paused".to_string());
+
+    let shown = overlay.draw(&frame, Instant::now());
+    assert!(lit(&shown, 0, 0, 40, 16));
+    assert!(lit(&shown, shown.width - 40, 0, 40, 16));
+    assert!(!lit(&shown, 0, shown.height - 32, shown.width, 32));
+}
+
+#[test]
+fn messages_fade_out() {
+    let frame = Image::new(64, 32, BLACK);
+    let start = Instant::now();
+    let mut overlay = Overlay::default();
+    overlay.message("Saved screenshot.png", start);
+    assert!(overlay.animating());
+
+    let bottom = |image: &Image| lit(image, 0, image.height - 20, 120, 20);
+    assert!(bottom(&overlay.draw(&frame, start)));
+    assert!(!bottom(
+        &overlay.draw(&frame, start + overlay::MESSAGE_TIME)
+    ));
+
+    overlay.expire(start + overlay::MESSAGE_TIME);
+    assert!(overlay.is_empty());
+}
+
+#[test]
+fn frame_rate_reports_speed() {
+    let mut rate = FrameRate::default();
+    let start = Instant::now();
+    assert_eq!(rate.text(), None);
+
+    // Twice as many frames as in a second at normal speed
+    for frame in 1..=120 {
+        rate.frame(start + Duration::from_millis(frame * 1000 / 120));
+    }
+    assert_eq!(rate.text().as_deref(), Some("120 FPS 200%"));
+}
@@ -0,0 +1,63 @@
+use lib::image::Rgb;
+use lib::palette::{Palette, parse_hex};
+
+#[test]
+fn hex_colours() {
+    assert_eq!(parse_hex("#12AbEf"), Some(Rgb(0x12, 0xAB, 0xEF)));
+    assert_eq!(parse_hex(" 12abef "), Some(Rgb(0x12, 0xAB, 0xEF)));
+    assert_eq!(parse_hex("#f80"), Some(Rgb(0xFF, 0x88, 0x00)));
+
+    for bad in ["", "#", "#1234", "#12345g", "#ggg", "#1234567", "#12é45"] {
+        assert_eq!(parse_hex(bad), None, "{}", bad);
+    }
+}
+
+#[test]
+fn colour_lists() {
+    let palette = Palette::parse("#000,#ffffff").unwrap();
+    assert_eq!(palette.name, "custom");
+    // The missing planes are a mix of both and the foreground
+    assert_eq!(
+        palette.colors,
+        [Rgb::BLACK, Rgb::WHITE, Rgb(0x7F, 0x7F, 0x7F), Rgb::WHITE]
+    );
+
+    let spec = "#112233,#445566,#778899,#AABBCC";
+    assert_eq!(Palette::parse(spec).unwrap().to_spec(), spec);
+
+    assert_eq!(
+        Palette::from_hex_list(&["#000"]).unwrap_err(),
+        "a palette needs 2 to 4 colours, got 1"
+    );
+    assert_eq!(
+        Palette::parse("#0,#1,#2,#3,#4").unwrap_err(),
+        "a palette needs 2 to 4 colours, got 5"
+    );
+    assert_eq!(
+        Palette::parse("#000,#fff,nope").unwrap_err(),
+        "invalid hex colour 'nope'"
+    );
+    assert_eq!(
+        Palette::parse("#000,").unwrap_err(),
+        "invalid hex colour ''"
+    );
+}
+
+#[test]
+fn names() {
+    assert_eq!(Palette::parse("AMBER").unwrap().name, "amber");
+    assert_eq!(Palette::parse("amber").unwrap().to_spec(), "amber");
+
+    let err = Palette::parse("mauve").unwrap_err();
+    assert!(err.starts_with("unknown palette 'mauve', expected one of: classic"));
+    for name in Palette::names() {
+        assert!(err.contains(name), "{}", name);
+    }
+
+    // Cycling visits every built-in palette
+    let mut palette = Palette::default();
+    for name in Palette::names().skip(1).chain(Palette::names().take(1)) {
+        palette = palette.next();
+        assert_eq!(palette.name, name);
+    }
+}
@@ -0,0 +1,101 @@
+use lib::display::Display;
+use lib::image::Rgb;
+use lib::palette::Palette;
+use lib::render::Pipeline;
+use lib::render::persistence::{Persistence, PersistenceMode};
+
+#[test]
+fn modes_by_spec() {
+    assert_eq!(
+        PersistenceMode::parse("fade:4"),
+        Ok(PersistenceMode::Fade(4))
+    );
+    assert_eq!(
+        PersistenceMode::parse("blend:2"),
+        Ok(PersistenceMode::Blend(2))
+    );
+    assert_eq!(PersistenceMode::Fade(4).to_spec(), "fade:4");
+    assert_eq!(PersistenceMode::Blend(2).to_spec(), "blend:2");
+
+    for bad in ["fade", "fade:0", "fade:-1", "fade:x", "glow:2"] {
+        assert!(PersistenceMode::parse(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn fade_dims_linearly_then_stops() {
+    let mut persistence = Persistence::new(PersistenceMode::Fade(4));
+    assert_eq!(persistence.apply(&[true, false]), [1.0, 0.0]);
+    assert!(!persistence.settling());
+
+    let mut levels = Vec::new();
+    for _ in 0..5 {
+        levels.push(persistence.apply(&[false, false])[0]);
+    }
+    assert_eq!(levels, [0.75, 0.5, 0.25, 0.0, 0.0]);
+    assert!(!persistence.settling());
+
+    // Lighting up again is immediate, even halfway through a fade
+    persistence.apply(&[true, false]);
+    persistence.apply(&[false, false]);
+    assert!(persistence.settling());
+    assert_eq!(persistence.apply(&[false, true]), [0.5, 1.0]);
+}
+
+#[test]
+fn blend_keeps_pixels_lit_for_n_frames() {
+    let mut persistence = Persistence::new(PersistenceMode::Blend(3));
+    assert_eq!(persistence.apply(&[true, false]), [1.0, 0.0]);
+    assert_eq!(persistence.apply(&[false, true]), [1.0, 1.0]);
+    assert_eq!(persistence.apply(&[false, false]), [1.0, 1.0]);
+    assert!(persistence.settling());
+    assert_eq!(persistence.apply(&[false, false]), [0.0, 1.0]);
+    assert_eq!(persistence.apply(&[false, false]), [0.0, 0.0]);
+    assert!(!persistence.settling());
+}
+
+#[test]
+fn resizing_starts_afresh() {
+    let mut persistence = Persistence::new(PersistenceMode::Fade(2));
+    persistence.apply(&[true, true]);
+    // A resolution switch drops the old levels
+    assert_eq!(persistence.apply(&[false, false, false]), [0.0, 0.0, 0.0]);
+}
+
+#[test]
+fn faded_pixels_mix_the_palette_colours() {
+    let palette = Palette::from_hex_list(&["#000000", "#C8641E"]).unwrap();
+    let mut pipeline = Pipeline::new(palette, Some(PersistenceMode::Fade(4)));
+    let mut display = Display::new();
+
+    display.set_pixel(0, 0, true);
+    assert_eq!(pipeline.render(&display).get(0, 0), Rgb(200, 100, 30));
+    assert!(!pipeline.animating());
+
+    display.set_pixel(0, 0, false);
+    // Three quarters lit, rounded to the nearest channel value
+    assert_eq!(pipeline.render(&display).get(0, 0), Rgb(150, 75, 23));
+    assert!(pipeline.animating());
+    for _ in 0..3 {
+        pipeline.render(&display);
+    }
+    assert_eq!(pipeline.render(&display).get(0, 0), Rgb::BLACK);
+    assert!(!pipeline.animating());
+}
+
+#[test]
+fn drawing_again_does_not_move_the_fade_on() {
+    let palette = Palette::from_hex_list(&["#000000", "#C8641E"]).unwrap();
+    let mut pipeline = Pipeline::new(palette, Some(PersistenceMode::Fade(4)));
+    let mut display = Display::new();
+
+    display.set_pixel(0, 0, true);
+    pipeline.render(&display);
+    display.set_pixel(0, 0, false);
+    pipeline.advance(&display);
+    // Redraws for a resize or a message show the same picture
+    for _ in 0..3 {
+        assert_eq!(pipeline.draw(&display).get(0, 0), Rgb(150, 75, 23));
+    }
+    assert!(pipeline.animating());
+}
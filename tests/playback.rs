@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use lib::chip::playback::Playback;
+use lib::chip::{Chip, FRAME_DURATION, RunOutcome};
+use lib::display::Framebuffer;
+use lib::frontend::headless::{HeadlessRenderer, NullAudio};
+use lib::frontend::{Action, InputSource, KeypadState, Renderer};
+
+// Counts emulated frames and remembers every status shown
+#[derive(Default)]
+struct Recorder {
+    frames: u32,
+    statuses: Vec<Option<String>>,
+}
+
+impl Renderer for Recorder {
+    fn present(&mut self, _frame: &Framebuffer) {}
+
+    fn end_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    fn set_status(&mut self, status: Option<&str>) {
+        self.statuses.push(status.map(str::to_string));
+    }
+}
+
+// Gives one batch of actions per poll, then asks for the menu
+struct Script(VecDeque<Vec<Action>>);
+
+impl InputSource for Script {
+    fn poll(&mut self) -> KeypadState {
+        KeypadState::default()
+    }
+
+    fn actions(&mut self) -> Vec<Action> {
+        self.0.pop_front().unwrap_or(vec![Action::Menu])
+    }
+}
+
+#[test]
+fn paused_games_only_run_advanced_frames() {
+    let mut chip = Chip::new();
+    chip.load_bytes(&[0x12, 0x00]).unwrap();
+    let mut renderer = Recorder::default();
+    let mut input = Script(VecDeque::from([
+        vec![Action::Pause],
+        vec![],
+        vec![Action::FrameAdvance, Action::FrameAdvance],
+        vec![],
+        vec![],
+        vec![],
+    ]));
+
+    let outcome = chip.interpret(&mut renderer, &mut input, &mut NullAudio);
+    assert_eq!(outcome, RunOutcome::Menu);
+    // The first frame, then two advanced ones
+    assert_eq!(renderer.frames, 3);
+    assert!(chip.playback().paused());
+    assert_eq!(renderer.statuses, [None, Some("Paused".to_string()), None]);
+}
+
+#[test]
+fn headless_runs_only_draw_the_frames_that_ran() {
+    let mut chip = Chip::new();
+    // Clears the screen every frame, so every frame that runs is drawn
+    chip.load_bytes(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+    let mut renderer = HeadlessRenderer::default();
+    let mut input = Script(VecDeque::from([
+        vec![Action::Pause],
+        vec![],
+        vec![Action::FrameAdvance],
+        vec![],
+    ]));
+
+    let outcome = chip.interpret(&mut renderer, &mut input, &mut NullAudio);
+    assert_eq!(outcome, RunOutcome::Menu);
+    assert_eq!(renderer.frames, 2);
+
+    // Steps outside of `interpret` ignore the pause
+    chip.step(&mut renderer, &mut input, &mut NullAudio)
+        .unwrap();
+    assert_eq!(renderer.frames, 3);
+}
+
+#[test]
+fn fast_forward_wins_over_slow_motion() {
+    let mut playback = Playback::default();
+    playback.fast_forward_rate = 8.0;
+    playback.handle(Action::SlowMotion);
+    assert_eq!(playback.frame_duration(), FRAME_DURATION.div_f32(0.25));
+    assert_eq!(playback.status().as_deref(), Some("Slow motion 0.25x"));
+
+    playback.handle(Action::FastForward(true));
+    assert_eq!(playback.rate(), 8.0);
+    assert_eq!(playback.status().as_deref(), Some("Fast forward 8x"));
+
+    playback.handle(Action::FastForward(false));
+    playback.handle(Action::SlowMotion);
+    assert_eq!(playback.frame_duration(), FRAME_DURATION);
+    assert_eq!(playback.status(), None);
+}
+
+#[test]
+fn frame_advance_pauses_a_running_game() {
+    let mut playback = Playback::default();
+    playback.handle(Action::FrameAdvance);
+    assert!(playback.paused());
+    assert!(!playback.next_frame());
+
+    playback.handle(Action::FrameAdvance);
+    assert!(playback.next_frame());
+    assert!(!playback.next_frame());
+
+    // Pausing again drops advances that have not run yet
+    playback.handle(Action::FrameAdvance);
+    playback.handle(Action::Pause);
+    assert!(playback.next_frame());
+    assert!(!playback.paused());
+}
+
+#[test]
+fn quitting_returns_and_the_game_can_run_again() {
+    let mut chip = Chip::new();
+    chip.load_bytes(&[0x12, 0x00]).unwrap();
+    let mut renderer = Recorder::default();
+
+    let mut input = Script(VecDeque::from([vec![], vec![Action::Quit, Action::Menu]]));
+    let outcome = chip.interpret(&mut renderer, &mut input, &mut NullAudio);
+    assert_eq!(outcome, RunOutcome::Quit);
+    assert_eq!(renderer.frames, 2);
+
+    let mut input = Script(VecDeque::new());
+    let outcome = chip.interpret(&mut renderer, &mut input, &mut NullAudio);
+    assert_eq!(outcome, RunOutcome::Menu);
+    assert_eq!(renderer.frames, 3);
+}
+
+// Asks to quit after a number of frames, like closing the window
+struct QuitAfter(u32);
+
+impl InputSource for QuitAfter {
+    fn poll(&mut self) -> KeypadState {
+        self.0 = self.0.saturating_sub(1);
+        KeypadState::default()
+    }
+
+    fn actions(&mut self) -> Vec<Action> {
+        if self.0 == 0 {
+            vec![Action::Quit]
+        } else {
+            vec![]
+        }
+    }
+}
+
+#[test]
+fn quitting_is_left_to_the_caller() {
+    let mut chip = Chip::new();
+    chip.load_bytes(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+    let mut renderer = HeadlessRenderer::default();
+
+    let outcome = chip.interpret(&mut renderer, &mut QuitAfter(3), &mut NullAudio);
+    assert_eq!(outcome, RunOutcome::Quit);
+    assert_eq!(renderer.frames, 3);
+}
+
+#[test]
+fn dropped_files_are_opened() {
+    let mut chip = Chip::new();
+    chip.load_bytes(&[0x12, 0x00]).unwrap();
+    let dropped = PathBuf::from("roms/BRIX");
+
+    let mut input = Script(VecDeque::from([vec![
+        Action::Menu,
+        Action::Open(dropped.clone()),
+    ]]));
+    let outcome = chip.interpret(&mut Recorder::default(), &mut input, &mut NullAudio);
+    assert_eq!(outcome, RunOutcome::Open(dropped));
+}
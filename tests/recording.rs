@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::path::PathBuf;
+
+use lib::capture::recording::{MAX_FRAME_DELAY, RecordFormat, Recorder};
+use lib::image::{Image, Rgb};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("rust_chip8_{}_{}", std::process::id(), name))
+}
+
+// Records each picture for its number of frames
+fn record(name: &str, format: RecordFormat, pictures: &[(Rgb, u64)]) -> PathBuf {
+    let path = temp_path(name);
+    let mut recorder = Recorder::new(&path, format);
+    for &(color, frames) in pictures {
+        for _ in 0..frames {
+            recorder.capture(&Image::new(4, 2, color)).unwrap();
+            recorder.tick();
+        }
+    }
+    recorder.finish().unwrap();
+    path
+}
+
+// Delays of each GIF frame, in centiseconds, and its top left colour
+fn gif_frames(path: &PathBuf) -> Vec<(u16, u8)> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(File::open(path).unwrap()).unwrap();
+    let mut frames = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        frames.push((frame.delay, frame.buffer[0]));
+    }
+    frames
+}
+
+// Delays of each APNG frame, in 60ths of a second, and its top left colour
+fn apng_frames(path: &PathBuf) -> Vec<(u16, u8)> {
+    let mut reader = png::Decoder::new(File::open(path).unwrap())
+        .read_info()
+        .unwrap();
+    let count = reader.info().animation_control().unwrap().num_frames;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let mut frames = Vec::new();
+    for _ in 0..count {
+        reader.next_frame(&mut buffer).unwrap();
+        let control = reader.info().frame_control().unwrap();
+        assert_eq!(control.delay_den, 60);
+        frames.push((control.delay_num, buffer[0]));
+    }
+    frames
+}
+
+const RED: Rgb = Rgb(200, 0, 0);
+const BLUE: Rgb = Rgb(10, 0, 100);
+
+#[test]
+fn unchanged_frames_are_merged() {
+    let pictures = [(RED, 3), (BLUE, 2), (RED, 1)];
+
+    let path = record("merged.gif", RecordFormat::Gif, &pictures);
+    // 3, 2 and 1 60ths of a second rounded on the absolute time: 5, 8, 10 cs
+    assert_eq!(gif_frames(&path), [(5, 200), (3, 10), (2, 200)]);
+    std::fs::remove_file(path).unwrap();
+
+    let path = record("merged.png", RecordFormat::Apng, &pictures);
+    assert_eq!(apng_frames(&path), [(3, 200), (2, 10), (1, 200)]);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn long_stills_are_split_instead_of_overflowing() {
+    let still = 2 * MAX_FRAME_DELAY + 7;
+    let pictures = [(RED, still), (BLUE, 1)];
+
+    let path = record("still.png", RecordFormat::Apng, &pictures);
+    let max = MAX_FRAME_DELAY as u16;
+    assert_eq!(
+        apng_frames(&path),
+        [(max, 200), (max, 200), (7, 200), (1, 10)]
+    );
+    std::fs::remove_file(path).unwrap();
+
+    let path = record("still.gif", RecordFormat::Gif, &pictures);
+    let frames = gif_frames(&path);
+    assert_eq!(frames.len(), 4);
+    let red: u64 = frames[..3].iter().map(|&(delay, _)| delay as u64).sum();
+    assert_eq!(red, (still * 100 + 30) / 60);
+    assert_eq!(frames[3].1, 10);
+    std::fs::remove_file(path).unwrap();
+}
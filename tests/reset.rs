@@ -0,0 +1,135 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use lib::assembler::assemble;
+use lib::cartridge::{self, CartOptions, Cartridge};
+use lib::chip::quirks::Quirks;
+use lib::chip::rom::{self, LoadError};
+use lib::chip::{Chip, MAX_PROGRAM_SIZE};
+use lib::frontend::headless::{HeadlessRenderer, NullAudio, NullInput};
+use lib::watch::FileWatcher;
+
+fn run(chip: &mut Chip, frames: usize) {
+    let mut renderer = HeadlessRenderer::default();
+    for _ in 0..frames {
+        chip.step(&mut renderer, &mut NullInput, &mut NullAudio)
+            .unwrap();
+    }
+}
+
+#[test]
+fn reset_clears_the_machine_and_keeps_settings() {
+    let mut chip = Chip::new();
+    chip.load_program("roms/IBM").unwrap();
+    let quirks = Quirks::parse("shift").unwrap();
+    chip.set_quirks(quirks);
+    chip.set_tickrate(30);
+    run(&mut chip, 30);
+    assert!(chip.framebuffer().to_text().contains('#'));
+
+    chip.reset().unwrap();
+    assert!(!chip.framebuffer().to_text().contains('#'));
+    assert_eq!(chip.program(), &fs::read("roms/IBM").unwrap()[..]);
+    assert_eq!((chip.quirks(), chip.tickrate()), (quirks, 30));
+
+    // And it runs the same as the first time
+    let mut fresh = Chip::new();
+    fresh.load_program("roms/IBM").unwrap();
+    fresh.set_tickrate(30);
+    run(&mut chip, 30);
+    run(&mut fresh, 30);
+    assert_eq!(chip.framebuffer().to_text(), fresh.framebuffer().to_text());
+}
+
+#[test]
+fn reset_reloads_the_rom_file() {
+    let path = std::env::temp_dir().join(format!("rust_chip8_reset_{}.ch8", std::process::id()));
+    fs::write(&path, [0x12, 0x00]).unwrap();
+    let mut chip = Chip::new();
+    chip.load_program(path.to_str().unwrap()).unwrap();
+    assert_eq!(chip.rom_path(), Some(path.as_path()));
+
+    fs::write(&path, [0x00, 0xE0, 0x12, 0x02]).unwrap();
+    chip.reset().unwrap();
+    assert_eq!(chip.program(), [0x00, 0xE0, 0x12, 0x02]);
+
+    // A ROM that no longer fits leaves the old one in place
+    fs::write(&path, vec![0; MAX_PROGRAM_SIZE + 1]).unwrap();
+    assert!(chip.reset().is_err());
+    assert_eq!(chip.program(), [0x00, 0xE0, 0x12, 0x02]);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn reset_reloads_cartridges() {
+    let path = std::env::temp_dir().join(format!("rust_chip8_reset_{}.gif", std::process::id()));
+    let save = |program: &[u8]| {
+        let cartridge = Cartridge {
+            program: program.to_vec(),
+            options: CartOptions::default(),
+        };
+        cartridge::save(&cartridge, &path).unwrap();
+    };
+    save(&[0x12, 0x00]);
+    let mut chip = Chip::new();
+    chip.load_program(path.to_str().unwrap()).unwrap();
+    assert_eq!(chip.rom_path(), Some(path.as_path()));
+
+    save(&[0x00, 0xE0, 0x12, 0x02]);
+    chip.reset().unwrap();
+    assert_eq!(chip.program(), [0x00, 0xE0, 0x12, 0x02]);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn watcher_waits_for_the_file_to_settle() {
+    let path = std::env::temp_dir().join(format!("rust_chip8_watch_{}.ch8", std::process::id()));
+    fs::write(&path, [0x12, 0x00]).unwrap();
+    let mut watcher = FileWatcher::new(&path);
+
+    thread::sleep(Duration::from_millis(300));
+    assert!(!watcher.changed());
+
+    fs::write(&path, [0x00, 0xE0, 0x12, 0x02]).unwrap();
+    thread::sleep(Duration::from_millis(300));
+    assert!(!watcher.changed());
+    thread::sleep(Duration::from_millis(300));
+    assert!(watcher.changed());
+    thread::sleep(Duration::from_millis(300));
+    assert!(!watcher.changed());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn hot_swap_keeps_the_screen() {
+    let mut chip = Chip::new();
+    chip.load_program("roms/IBM").unwrap();
+    run(&mut chip, 30);
+    let screen = chip.framebuffer().to_text();
+
+    let program = assemble(": main\n\tloop again\n").unwrap();
+    chip.hot_swap(&program).unwrap();
+    assert_eq!(chip.program(), &program[..]);
+    run(&mut chip, 5);
+    assert_eq!(chip.framebuffer().to_text(), screen);
+}
+
+#[test]
+fn octo_sources_load_and_report_errors() {
+    let path = std::env::temp_dir().join(format!("rust_chip8_source_{}.8o", std::process::id()));
+    fs::write(&path, ": main\n\tclear\n\tloop again\n").unwrap();
+    let mut chip = Chip::new();
+    chip.load_program(path.to_str().unwrap()).unwrap();
+    assert_eq!(chip.program(), [0x00, 0xE0, 0x12, 0x02]);
+
+    fs::write(&path, ": main\n\tclear\n\tjump nowhere\n").unwrap();
+    let err = rom::read(&path).unwrap_err();
+    assert!(matches!(err, LoadError::Assembly(_)));
+    assert!(err.to_string().starts_with("line 3: "));
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,181 @@
+use lib::chip::Chip;
+use lib::display::{HEIGHT, WIDTH};
+use lib::frontend::KeypadState;
+use lib::image::{Image, Rgb};
+use lib::render::scaler::Scaler;
+
+// Parses a plain (P1) PBM file, 1 is a lit pixel
+fn read_pbm(text: &str) -> Image {
+    let mut tokens = text
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split_whitespace());
+
+    assert_eq!(tokens.next(), Some("P1"));
+    let width: usize = tokens.next().unwrap().parse().unwrap();
+    let height: usize = tokens.next().unwrap().parse().unwrap();
+    let data: Vec<bool> = tokens
+        .flat_map(|token| token.chars())
+        .map(|c| c == '1')
+        .collect();
+
+    Image::from_pixels(&data, width, height, Rgb::BLACK, Rgb::WHITE)
+}
+
+// Parses a plain (P2) PGM file into grey pixels
+fn read_pgm(text: &str) -> Image {
+    let mut tokens = text
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .flat_map(|line| line.split_whitespace());
+
+    assert_eq!(tokens.next(), Some("P2"));
+    let width: usize = tokens.next().unwrap().parse().unwrap();
+    let height: usize = tokens.next().unwrap().parse().unwrap();
+    assert_eq!(tokens.next(), Some("255"));
+    let pixels = tokens
+        .map(|token| {
+            let grey = token.parse().unwrap();
+            Rgb(grey, grey, grey)
+        })
+        .collect();
+
+    Image {
+        width,
+        height,
+        pixels,
+    }
+}
+
+fn from_rows(rows: &[&str]) -> Image {
+    let data: Vec<bool> = rows
+        .iter()
+        .flat_map(|r| r.chars())
+        .map(|c| c == '#')
+        .collect();
+    Image::from_pixels(&data, rows[0].len(), rows.len(), Rgb::BLACK, Rgb::WHITE)
+}
+
+fn ibm_logo() -> Image {
+    let mut chip = Chip::new();
+    chip.load_program("roms/IBM").unwrap();
+    for _ in 0..30 {
+        chip.run_frame(KeypadState::default());
+    }
+
+    Image::from_pixels(
+        &chip.framebuffer().data,
+        WIDTH,
+        HEIGHT,
+        Rgb::BLACK,
+        Rgb::WHITE,
+    )
+}
+
+#[test]
+fn scale2x_smooths_diagonal() {
+    let diagonal = from_rows(&[".....", ".#...", "..#..", "...#.", "....."]);
+
+    assert_eq!(
+        Scaler::Scale2x.apply(&diagonal),
+        from_rows(&[
+            "..........",
+            "..........",
+            "..##......",
+            "..###.....",
+            "...###....",
+            "....###...",
+            ".....###..",
+            "......##..",
+            "..........",
+            "..........",
+        ])
+    );
+}
+
+#[test]
+fn eagle_drops_isolated_pixel() {
+    let dot = from_rows(&["...", ".#.", "..."]);
+
+    assert_eq!(Scaler::Eagle.apply(&dot), from_rows(&["......"; 6]));
+}
+
+#[test]
+fn eagle_rounds_block_corners() {
+    let block = from_rows(&["....", ".##.", ".##.", "...."]);
+
+    assert_eq!(
+        Scaler::Eagle.apply(&block),
+        from_rows(&[
+            "........", "........", "...##...", "..####..", "..####..", "...##...", "........",
+            "........",
+        ])
+    );
+}
+
+#[test]
+fn nearest_is_identity() {
+    let logo = ibm_logo();
+    assert_eq!(Scaler::Nearest.apply(&logo), logo);
+}
+
+#[test]
+fn ibm_logo_scale2x() {
+    assert_eq!(
+        Scaler::Scale2x.apply(&ibm_logo()),
+        read_pbm(include_str!("fixtures/ibm_scale2x.pbm"))
+    );
+}
+
+#[test]
+fn ibm_logo_scale3x() {
+    assert_eq!(
+        Scaler::Scale3x.apply(&ibm_logo()),
+        read_pbm(include_str!("fixtures/ibm_scale3x.pbm"))
+    );
+}
+
+#[test]
+fn ibm_logo_scale4x() {
+    assert_eq!(
+        Scaler::Scale4x.apply(&ibm_logo()),
+        read_pbm(include_str!("fixtures/ibm_scale4x.pbm"))
+    );
+}
+
+#[test]
+fn ibm_logo_eagle() {
+    assert_eq!(
+        Scaler::Eagle.apply(&ibm_logo()),
+        read_pbm(include_str!("fixtures/ibm_eagle.pbm"))
+    );
+}
+
+#[test]
+fn smooth2x_blends_the_corners_of_a_dot() {
+    let dot = from_rows(&["...", ".#.", "..."]);
+    let scaled = Scaler::Smooth2x.apply(&dot);
+
+    // 14 parts of white to 2 of black in each corner of the dot
+    let corner = Rgb(223, 223, 223);
+    for (x, y) in [(2, 2), (3, 2), (2, 3), (3, 3)] {
+        assert_eq!(scaled.get(x, y), corner);
+    }
+    assert_eq!(scaled.get(1, 2), Rgb::BLACK);
+    assert_eq!(scaled.get(2, 1), Rgb::BLACK);
+}
+
+#[test]
+fn smooth2x_leaves_flat_areas_alone() {
+    let flat = Image::new(4, 4, Rgb(10, 200, 30));
+    assert_eq!(Scaler::Smooth2x.apply(&flat), flat.scale(2));
+}
+
+#[test]
+// Recorded from this scaler to catch changes, not from a reference hq2x
+fn ibm_logo_smooth2x() {
+    assert_eq!(
+        Scaler::Smooth2x.apply(&ibm_logo()),
+        read_pgm(include_str!("fixtures/ibm_smooth2x.pgm"))
+    );
+}
@@ -0,0 +1,134 @@
+use std::io::Cursor;
+
+use lib::capture::video::{VideoFormat, VideoWriter};
+use lib::capture::wav::SAMPLE_RATE;
+use lib::display::{Display, HEIGHT, WIDTH};
+use lib::frontend::headless::HeadlessRenderer;
+use lib::frontend::video::{VideoRenderer, WavAudio};
+use lib::frontend::{AudioSink, Renderer};
+use lib::image::{Image, Rgb};
+use lib::palette::Palette;
+use lib::render::Pipeline;
+
+const WAV_HEADER: usize = 44;
+
+// Samples written by `WavAudio` for the buzzer state of each frame
+fn wav_samples(playing: &[bool], tone_hz: f32, volume: f32) -> Vec<i16> {
+    let mut audio = WavAudio::new(Cursor::new(Vec::new()), tone_hz, volume).unwrap();
+    for &playing in playing {
+        audio.set_playing(playing);
+    }
+    let wav = audio.finish().unwrap().into_inner();
+    wav[WAV_HEADER..]
+        .chunks(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect()
+}
+
+#[test]
+fn y4m_has_a_header_then_one_frame_per_picture() {
+    let mut writer = VideoWriter::new(Vec::new(), VideoFormat::Y4m);
+    for _ in 0..3 {
+        writer.write_frame(&Image::new(8, 4, Rgb::WHITE)).unwrap();
+    }
+    assert_eq!(writer.frames(), 3);
+    let video = writer.finish().unwrap();
+
+    let header = b"YUV4MPEG2 W8 H4 F60:1 Ip A1:1 C444\n";
+    assert!(video.starts_with(header));
+    // 4:4:4, three full planes
+    let frame = b"FRAME\n".len() + 8 * 4 * 3;
+    assert_eq!(video.len(), header.len() + 3 * frame);
+    assert!(video[header.len()..].starts_with(b"FRAME\n"));
+    // White is luma 235 in limited range, with neutral chroma
+    assert_eq!(video[header.len() + 6], 235);
+    assert_eq!(video[header.len() + 6 + 8 * 4], 128);
+}
+
+#[test]
+fn raw_video_is_bare_rgb() {
+    let mut writer = VideoWriter::new(Vec::new(), VideoFormat::Raw);
+    writer.write_frame(&Image::new(8, 4, Rgb(1, 2, 3))).unwrap();
+    writer.write_frame(&Image::new(8, 4, Rgb(1, 2, 3))).unwrap();
+    let video = writer.finish().unwrap();
+
+    assert_eq!(video.len(), 2 * 8 * 4 * 3);
+    assert_eq!(&video[..3], [1, 2, 3]);
+
+    let mut writer = VideoWriter::new(Vec::new(), VideoFormat::Raw);
+    writer.write_frame(&Image::new(8, 4, Rgb::BLACK)).unwrap();
+    assert!(writer.write_frame(&Image::new(4, 4, Rgb::BLACK)).is_err());
+}
+
+#[test]
+fn every_emulated_frame_is_written() {
+    let mut pipeline = Pipeline::new(Palette::default(), None);
+    pipeline.scale = 2;
+    let (width, height) = pipeline.output_size();
+    let mut video = Vec::new();
+    let writer = VideoWriter::new(&mut video, VideoFormat::Raw);
+    let mut renderer = VideoRenderer::new(HeadlessRenderer::default(), pipeline, writer);
+
+    // Only the first frame draws anything, the others repeat it
+    renderer.present(&Display::new());
+    for _ in 0..5 {
+        renderer.end_frame();
+    }
+    let inner = renderer.finish().unwrap();
+    assert_eq!(inner.frames, 1);
+    assert_eq!(video.len(), 5 * width * height * 3);
+}
+
+#[test]
+fn raw_frames_match_the_pipeline_size() {
+    let mut pipeline = Pipeline::new(Palette::default(), None);
+    pipeline.scale = 3;
+    let (width, height) = pipeline.output_size();
+    assert_eq!((width, height), (WIDTH * 3, HEIGHT * 3));
+
+    let mut writer = VideoWriter::new(Vec::new(), VideoFormat::Raw);
+    for _ in 0..4 {
+        let image = pipeline.render(&Display::new());
+        writer.write_frame(&image).unwrap();
+    }
+    assert_eq!(writer.finish().unwrap().len(), 4 * width * height * 3);
+}
+
+#[test]
+fn larger_screens_give_larger_frames() {
+    let mut pipeline = Pipeline::new(Palette::default(), None);
+    pipeline.scale = 2;
+    let image = pipeline.render(&Display::with_size(128, 64));
+    assert_eq!((image.width, image.height), (256, 128));
+    assert_eq!(pipeline.output_size(), (256, 128));
+}
+
+#[test]
+fn wav_keeps_in_step_with_the_frames() {
+    let frames = 60 * 7 + 1;
+    let samples = wav_samples(&vec![false; frames], 440.0, 0.5);
+    assert_eq!(samples.len(), frames * SAMPLE_RATE as usize / 60);
+    assert!(samples.iter().all(|&sample| sample == 0));
+
+    // One frame of buzzer between silent ones
+    let samples = wav_samples(&[false, true, false], 440.0, 0.5);
+    let frame = SAMPLE_RATE as usize / 60;
+    assert_eq!(samples.len(), 3 * frame);
+    assert!(samples[..frame].iter().all(|&sample| sample == 0));
+    assert!(samples[frame..2 * frame].iter().all(|&sample| sample != 0));
+    assert!(samples[2 * frame..].iter().all(|&sample| sample == 0));
+}
+
+#[test]
+fn wav_follows_the_tone_and_volume() {
+    let samples = wav_samples(&[true; 60], 441.0, 0.5);
+    let peak = (0.5 * i16::MAX as f32) as i16;
+    assert!(samples.iter().all(|&sample| sample.abs() == peak));
+    // A square wave changes sign twice per period
+    let flips = samples.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    assert!((881..=883).contains(&flips), "{}", flips);
+
+    // Muted
+    let samples = wav_samples(&[true; 10], 441.0, 0.0);
+    assert!(samples.iter().all(|&sample| sample == 0));
+}